@@ -2,11 +2,26 @@
 //! that are specific to the guidebook-plan application.
 //!
 use crate::internal::*;
-use std::path::PathBuf;
-use std::process::Command;
+use crate::repo::{self, Repo};
+use std::path::{Path, PathBuf};
 
+/// Location of the plan file relative to the guidebook root (i.e. the
+/// path git uses to refer to it).
+pub const PLAN_RELATIVE_PATH: &str = "guidebook-plan/plan.yaml";
+
+/// Entry point for working with the user's guidebook data: finds the data
+/// directory, loads and saves the plan and today's shifts, and opens the git
+/// repository.
 pub struct App {
-    git_dir: Option<PathBuf>,
+    /// The home directory the data is kept under, if not the user's
+    home: Option<PathBuf>,
+    repo: Option<Box<dyn Repo>>,
+}
+
+impl Default for App {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl App {
@@ -14,43 +29,52 @@ impl App {
     // Constructor
     //------------------------------------------------------------------------//
 
+    /// Creates an app for the data directory in the user's home directory.
     pub fn new() -> Self {
-        Self { git_dir: None }
+        Self {
+            home: None,
+            repo: None,
+        }
+    }
+
+    /// Creates an app for the data kept under another home directory, e.g.
+    /// a scratch directory in tests.
+    pub fn with_home(home: &Path) -> Self {
+        Self {
+            home: Some(home.to_path_buf()),
+            repo: None,
+        }
     }
 
     //------------------------------------------------------------------------//
     // Configuration related
     //------------------------------------------------------------------------//
 
-    pub fn git_dir(&mut self) -> Result<PathBuf> {
-        if self.git_dir.is_none() {
-            self.git_dir = Some(self.guidebook_root(false)?);
+    /// The home directory the data is kept under.
+    fn home(&self) -> Result<PathBuf> {
+        match &self.home {
+            Some(home) => Ok(home.clone()),
+            None => dirs::home_dir().ok_or_else(|| invalid!("HOME directory not found")),
         }
-        Ok(self.git_dir.clone().unwrap())
     }
 
+    /// The guidebook data directory, ~/.local/share/guidebook.  Fails with
+    /// [`Error::MissingRoot`] if it doesn't exist, unless `skip_check` is set.
     pub fn guidebook_root(&self, skip_check: bool) -> Result<PathBuf> {
-        let home = dirs::home_dir().ok_or_else(|| anyhow!("HOME directory not found"))?;
-        let basedir = home.join(".local/share/guidebook");
+        let basedir = self.home()?.join(".local/share/guidebook");
 
         if skip_check {
             return Ok(basedir);
         }
 
         if !basedir.exists() {
-            let pretty_basedir = basedir
-                .to_string_lossy()
-                .replace(&home.to_string_lossy().to_string(), "~");
-
-            cprintln!("#F90", "Guidebook root not found at {}", pretty_basedir);
-            cprintln!("#F90", "Perhaps you need to run:");
-            cprintln!("#DD8", "guidebook-plan clone");
-            std::process::exit(1);
+            return Err(Error::MissingRoot(basedir));
         }
 
         Ok(basedir)
     }
 
+    /// Whether the data directory has been created.
     pub fn guidebook_root_exists(&self) -> bool {
         let Ok(root) = self.guidebook_root(true) else {
             return false;
@@ -58,25 +82,106 @@ impl App {
         root.exists()
     }
 
+    /// The data directory with the home directory written as `~`.
     pub fn guidebook_root_pretty(&self) -> Result<String> {
         let guidebook_root = self.guidebook_root(true)?;
-        let home = dirs::home_dir().unwrap();
+        let home = self.home()?;
         let pretty_path = guidebook_root
             .to_string_lossy()
             .replace(&home.to_string_lossy().to_string(), "~");
         Ok(pretty_path)
     }
 
+    /// Path of the plan file in the data directory.
     pub fn find_data_filename(&self) -> Result<PathBuf> {
         let guidebook_root = self.guidebook_root(false)?;
-        Ok(guidebook_root.join("guidebook-plan/plan.yaml"))
+        Ok(guidebook_root.join(PLAN_RELATIVE_PATH))
+    }
+
+    //------------------------------------------------------------------------//
+    // Plan loading
+    //------------------------------------------------------------------------//
+
+    /// Loads the plan from the working tree and checks that it's valid.
+    pub fn load_plan(&self) -> Result<Plan> {
+        let filename = self.find_data_filename()?;
+        let text = read_plan_file(&filename)?;
+        Plan::from_yaml_validated(&text).map_err(|e| e.in_file(filename))
+    }
+
+    /// Loads the plan from the working tree along with its source text, for
+    /// commands that modify the plan.
+    pub fn load_plan_document(&self) -> Result<PlanDocument> {
+        let filename = self.find_data_filename()?;
+        let text = read_plan_file(&filename)?;
+        PlanDocument::parse(&text).map_err(|e| e.in_file(filename))
+    }
+
+    /// Writes a plan document back to the plan file.
+    pub fn save_plan_document(&self, doc: &PlanDocument) -> Result<()> {
+        let filename = self.find_data_filename()?;
+        std::fs::write(filename, doc.render())?;
+        Ok(())
+    }
+
+    /// Loads the plan as it was committed at the given git revision.
+    pub fn load_plan_at(&mut self, rev: &str) -> Result<Plan> {
+        let text = self.repo()?.file_at(rev, PLAN_RELATIVE_PATH)?;
+        Plan::from_yaml_validated(&text)
+            .map_err(|e| e.in_file(PathBuf::from(format!("{}:{}", rev, PLAN_RELATIVE_PATH))))
+    }
+
+    /// Finds the most recent commit of the plan file made on or before the
+    /// given date, i.e. the revision of the plan that was in effect that day.
+    /// Returns the abbreviated commit hash and the commit date.
+    pub fn plan_revision_as_of(
+        &mut self,
+        date: chrono::NaiveDate,
+    ) -> Result<Option<(String, String)>> {
+        self.repo()?.last_change_as_of(PLAN_RELATIVE_PATH, date)
+    }
+
+    //------------------------------------------------------------------------//
+    // Today's shifts
+    //------------------------------------------------------------------------//
+
+    /// Shifts are local to this machine and this day, so they're kept out of
+    /// the data repository.
+    pub fn shifts_filename(&self) -> Result<PathBuf> {
+        Ok(self.home()?.join(".local/state/guidebook-plan/shifts.yaml"))
+    }
+
+    /// Loads the shifts made on the given date.  Shifts from any other day
+    /// have expired and are ignored.
+    pub fn load_shifts(&self, date: chrono::NaiveDate) -> Result<DayShifts> {
+        let filename = self.shifts_filename()?;
+        if !filename.exists() {
+            return Ok(DayShifts::new(date));
+        }
+        let text = std::fs::read_to_string(filename)?;
+        let shifts: DayShifts = serde_yaml::from_str(&text)?;
+        if shifts.date != date {
+            return Ok(DayShifts::new(date));
+        }
+        Ok(shifts)
+    }
+
+    /// Saves today's shifts, replacing those of any earlier day.
+    pub fn save_shifts(&self, shifts: &DayShifts) -> Result<()> {
+        let filename = self.shifts_filename()?;
+        if let Some(dir) = filename.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(filename, serde_yaml::to_string(shifts)?)?;
+        Ok(())
     }
 
     //------------------------------------------------------------------------//
     // File utilities
     //------------------------------------------------------------------------//
 
-    pub fn dir_exists(&self, path: &PathBuf) -> bool {
+    /// Whether the path is an existing directory.
+    pub fn dir_exists(&self, path: &Path) -> bool {
         path.is_dir()
     }
 
@@ -84,24 +189,24 @@ impl App {
     // git related
     //-----------------------------------------------------------------------//
 
-    pub fn git_status(&mut self) -> Result<String> {
-        self.run_git_cmd(&["status", "--porcelain"])
+    /// The data repository, opened on first use.
+    pub fn repo(&mut self) -> Result<&dyn Repo> {
+        if self.repo.is_none() {
+            let root = self.guidebook_root(false)?;
+            self.repo = Some(repo::open(&root)?);
+        }
+        Ok(self.repo.as_deref().unwrap())
     }
 
-    pub fn run_git_cmd(&mut self, args: &[&str]) -> Result<String> {
-        let git_dir = self.git_dir()?;
-        let output = Command::new("git")
-            .args(args)
-            .current_dir(git_dir)
-            .output()?;
-
-        if !output.status.success() {
-            return Err(anyhow!(
-                "Git command failed: {}",
-                String::from_utf8_lossy(&output.stderr)
-            ));
-        }
+    /// Paths of the files with uncommitted changes.
+    pub fn git_status(&mut self) -> Result<Vec<String>> {
+        self.repo()?.status()
+    }
+}
 
-        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+fn read_plan_file(filename: &Path) -> Result<String> {
+    if !filename.exists() {
+        return Err(Error::MissingPlan(filename.to_path_buf()));
     }
+    Ok(std::fs::read_to_string(filename)?)
 }
@@ -0,0 +1,74 @@
+use crate::internal::*;
+
+#[derive(clap::Subcommand)]
+pub enum ActivityCommand {
+    /// add an activity to a routine
+    Add {
+        /// routine name or 1-based position
+        routine: String,
+        text: String,
+        /// day the activity applies to (e.g. thu, weekdays, everyday)
+        #[arg(long, default_value = "default")]
+        day: DayOfWeek,
+    },
+    /// remove an activity from a routine
+    Remove {
+        /// routine name or 1-based position
+        routine: String,
+        /// the activity text or its 1-based position in the day's list
+        activity: String,
+        /// day the activity applies to (e.g. thu, weekdays, everyday)
+        #[arg(long, default_value = "default")]
+        day: DayOfWeek,
+    },
+}
+
+/// Edits the activities of a routine without needing to open an editor.
+///
+pub fn command_activity(command: ActivityCommand) -> Result<()> {
+    let app = App::new();
+    let mut doc = app.load_plan_document()?;
+    let plan = doc.plan();
+
+    let message = match command {
+        ActivityCommand::Add { routine, text, day } => {
+            let index = plan.find_routine(&routine)?;
+            let entry = doc.routine_mut(index);
+            entry
+                .activities
+                .entry(day.clone())
+                .or_default()
+                .push(text.clone());
+            format!("Added '{}' to [{}](key) ({})", text, entry.name, day.key())
+        }
+        ActivityCommand::Remove {
+            routine,
+            activity,
+            day,
+        } => {
+            let index = plan.find_routine(&routine)?;
+            let entry = doc.routine_mut(index);
+            let name = entry.name.clone();
+            let list = entry
+                .activities
+                .get_mut(&day)
+                .ok_or_else(|| anyhow!("[{}] has no activities for '{}'", name, day.key()))?;
+
+            let position = match activity.parse::<usize>() {
+                Ok(p) if p >= 1 && p <= list.len() => p - 1,
+                _ => list.iter().position(|a| *a == activity).ok_or_else(|| {
+                    anyhow!("No activity '{}' in {} ({})", activity, name, day.key())
+                })?,
+            };
+            let removed = list.remove(position);
+            if list.is_empty() {
+                entry.activities.remove(&day);
+            }
+            format!("Removed '{}' from [{}](key) ({})", removed, name, day.key())
+        }
+    };
+
+    app.save_plan_document(&doc)?;
+    cprintln!("success", "✓ {}", message);
+    Ok(())
+}
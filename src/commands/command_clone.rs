@@ -1,7 +1,6 @@
 use crate::internal::*;
 use std::io::{self, Write};
 use std::path::PathBuf;
-use std::process::Command;
 
 /// Clones an existing remote repository into the local guidebook root directory.
 ///
@@ -41,35 +40,23 @@ fn do_clone(app: &App) -> Result<()> {
     io::stdin().read_line(&mut input)?;
     let username = input.trim();
     if username.is_empty() {
-        cprintln!(
-            "warn",
+        return Err(anyhow!(
             "GitHub username is required to initialize the repository."
-        );
-        std::process::exit(1);
+        ));
     }
 
     let repo_name = format!("{}/guidebook-local.git", username);
     let parent_dir = guidebook_root.parent().unwrap();
     std::fs::create_dir_all(parent_dir)?;
 
-    let root_pretty = app.guidebook_root_pretty()?;
-    let args = [
-        "clone",
-        &format!("git@github.com:{}", repo_name),
-        &guidebook_root.to_string_lossy(),
-    ];
-    let mut pretty_args = args.clone();
-    pretty_args[2] = root_pretty.as_str();
-    cprintln!("", "Running [git {}](command)", pretty_args.join(" "));
-
-    let output = Command::new("git").args(&args).output()?;
-    if !output.status.success() {
-        cprintln!(
-            "error",
-            "Failed to clone repository. Please check the above error and try again."
-        );
-        std::process::exit(1);
-    }
+    let url = format!("git@github.com:{}", repo_name);
+    cprintln!(
+        "",
+        "Cloning [{}](command) into [{}](filename)",
+        url,
+        app.guidebook_root_pretty()?
+    );
+    guidebook_plan::repo::clone(&url, &guidebook_root, false)?;
     Ok(())
 }
 
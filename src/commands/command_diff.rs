@@ -0,0 +1,94 @@
+use crate::internal::*;
+
+/// Shows the routine-level changes between two revisions of the plan.
+///
+/// With no revisions the working tree is compared against HEAD. With a
+/// single revision the working tree is compared against that revision.
+///
+pub fn command_diff(
+    rev1: Option<String>,
+    rev2: Option<String>,
+    width: Option<usize>,
+) -> Result<()> {
+    let mut app = App::new();
+
+    let old_rev = rev1.unwrap_or_else(|| "HEAD".to_string());
+    let old = app.load_plan_at(&old_rev)?;
+    let (new, new_label) = match rev2 {
+        Some(rev) => (app.load_plan_at(&rev)?, rev),
+        None => (app.load_plan()?, "working tree".to_string()),
+    };
+
+    cprintln!(
+        "h1",
+        "Plan changes: [{}](key) → [{}](key)",
+        old_rev,
+        new_label
+    );
+    cprintln!("rule", "{}", rule(width.unwrap_or_else(terminal_width)));
+
+    let changes = diff_plans(&old, &new);
+    if changes.is_empty() {
+        cprintln!("success_dim", "No routine changes.");
+        return Ok(());
+    }
+
+    for change in changes {
+        match change {
+            RoutineChange::Added(routine) => {
+                cprintln!(
+                    "success",
+                    "\\+ {} ({})",
+                    routine.name,
+                    pretty_timing(&routine)
+                );
+            }
+            RoutineChange::Removed(routine) => {
+                cprintln!(
+                    "error",
+                    "\\- {} ({})",
+                    routine.name,
+                    pretty_timing(&routine)
+                );
+            }
+            RoutineChange::Retimed { name, fields } => {
+                cprintln!("warn", "~ {}", name);
+                for (field, before, after) in fields {
+                    cprintln!(
+                        "txt",
+                        "      {:<9} [{}](error) → [{}](success)",
+                        format!("{}:", field),
+                        before.as_deref().unwrap_or("(none)"),
+                        after.as_deref().unwrap_or("(none)")
+                    );
+                }
+            }
+            RoutineChange::Activities {
+                name,
+                day,
+                added,
+                removed,
+            } => {
+                cprintln!("warn", "~ {} [{}](key)", name, day.key());
+                for activity in removed {
+                    cprintln!("error", "      \\- {}", activity);
+                }
+                for activity in added {
+                    cprintln!("success", "      \\+ {}", activity);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Summarizes the timing fields of a routine, e.g. "start 9:00 am, duration 3h".
+fn pretty_timing(routine: &Routine) -> String {
+    routine
+        .timing_fields()
+        .into_iter()
+        .filter_map(|(field, value)| value.map(|value| format!("{} {}", field, value)))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
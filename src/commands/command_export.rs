@@ -0,0 +1,99 @@
+use crate::export::{agenda, AgendaDay, AgendaFormat};
+use crate::internal::*;
+use chrono::{Datelike, Duration, Local};
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum ExportFormat {
+    /// printable web page with the week
+    Html,
+    /// image of the week
+    Svg,
+    /// Markdown agenda, e.g. for a daily note
+    Md,
+    /// org-mode agenda
+    Org,
+}
+
+/// Writes the plan in the given format to a file, or to stdout if no file
+/// is given.  The html and svg formats draw the week; the md and org formats
+/// list the routines of one day, today by default, or of the whole week.
+///
+pub fn command_export(
+    format: ExportFormat,
+    day: Option<DaySelector>,
+    week: bool,
+    output: Option<PathBuf>,
+) -> Result<()> {
+    let app = App::new();
+    let plan = app.load_plan()?;
+
+    let text = match format {
+        ExportFormat::Html | ExportFormat::Svg if day.is_some() => {
+            return Err(anyhow!(
+                "--day only applies to the md and org formats; html and svg show the week"
+            ));
+        }
+        ExportFormat::Html | ExportFormat::Svg if week => {
+            return Err(anyhow!(
+                "--week only applies to the md and org formats; html and svg always show the week"
+            ));
+        }
+        ExportFormat::Html => crate::export::week_html(&plan)?,
+        ExportFormat::Svg => crate::export::week_svg(&plan)?,
+        ExportFormat::Md | ExportFormat::Org => {
+            let format = match format {
+                ExportFormat::Org => AgendaFormat::Org,
+                _ => AgendaFormat::Markdown,
+            };
+            export_agenda(&app, &plan, day, week, format)?
+        }
+    };
+
+    match output {
+        Some(filename) => {
+            std::fs::write(&filename, text)?;
+            cprintln!(
+                "success",
+                "✓ Exported the plan to [{}](filename)",
+                filename.display()
+            );
+        }
+        None => print!("{}", text),
+    }
+    Ok(())
+}
+
+fn export_agenda(
+    app: &App,
+    plan: &Plan,
+    day: Option<DaySelector>,
+    week: bool,
+    format: AgendaFormat,
+) -> Result<String> {
+    let today = Local::now().date_naive();
+    let days = if week {
+        let monday = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+        (0..7)
+            .map(|i| DaySelector::Date(monday + Duration::days(i)).resolve())
+            .collect()
+    } else {
+        vec![day.unwrap_or(DaySelector::Date(today)).resolve()]
+    };
+
+    let shifts = app.load_shifts(today)?;
+    let mut agenda_days = Vec::new();
+    for (day, date) in days {
+        let mut schedule = Schedule::resolve(plan, day)?;
+        // Like `show`, today's agenda includes today's shifts
+        if date == Some(today) {
+            for shift in &shifts.shifts {
+                schedule.apply_shift(shift);
+            }
+        }
+        agenda_days.push(AgendaDay { schedule, date });
+    }
+
+    let title = week.then(|| format!("Week of {}", agenda_days[0].date.unwrap_or(today)));
+    Ok(agenda(&agenda_days, title.as_deref(), format))
+}
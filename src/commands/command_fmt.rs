@@ -0,0 +1,119 @@
+use crate::internal::*;
+
+/// Rewrites plan.yaml in a canonical form so that diffs only show real
+/// changes; see `PlanDocument::format`.  With `check`, the file is left
+/// alone and the command fails if it isn't already formatted, e.g. for a
+/// pre-commit hook.
+///
+pub fn command_fmt(check: bool) -> Result<()> {
+    let app = App::new();
+    let filename = app.find_data_filename()?;
+    let doc = app.load_plan_document()?;
+    let text = std::fs::read_to_string(&filename)?;
+    let formatted = format_document(doc);
+
+    let pretty = filename.display();
+    if formatted == text {
+        cprintln!("success_dim", "✓ [{}](filename) is formatted", pretty);
+        return Ok(());
+    }
+    if check {
+        return Err(anyhow!(
+            "{} is not formatted; run 'guidebook-plan fmt' to format it",
+            pretty
+        ));
+    }
+
+    std::fs::write(&filename, formatted)?;
+    cprintln!("success", "✓ Formatted [{}](filename)", pretty);
+    Ok(())
+}
+
+/// The text of the document in canonical form.
+fn format_document(mut doc: PlanDocument) -> String {
+    doc.format();
+    let rendered = doc.render();
+    // Trailing whitespace is only dropped if that doesn't change the plan,
+    // which it could inside a multi-line string
+    let trimmed = trim_line_ends(&rendered);
+    match Plan::from_yaml(&trimmed) {
+        Ok(plan) if plan == doc.plan() => trimmed,
+        _ => rendered,
+    }
+}
+
+/// Removes trailing whitespace from each line and makes the text end with
+/// a single newline.
+fn trim_line_ends(text: &str) -> String {
+    let mut out: String = text
+        .lines()
+        .map(|line| format!("{}\n", line.trim_end()))
+        .collect();
+    let len = out.trim_end().len();
+    out.truncate(len);
+    out.push('\n');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAN: &str = "daily_routine:
+  - name: Wake
+    duration: 1.5h   # slow mornings
+    start: 7:00 AM
+    activities:
+      # Weekly run
+      mon:
+        - Run
+      default:
+        - Stretch  
+      weekdays:
+        - Email
+  - name: Work
+    duration: FILL
+  - name: Lunch
+    start: Noon
+    duration: 90 min
+  - name: Walk
+    after: Lunch+1.5h
+    duration: abc
+";
+
+    fn format(text: &str) -> String {
+        format_document(PlanDocument::parse(text).unwrap())
+    }
+
+    #[test]
+    fn formats_fields_days_and_durations() {
+        let expected = "daily_routine:
+  - name: Wake
+    start: 7:00 am
+    duration: 1h30m   # slow mornings
+    activities:
+      default:
+        - Stretch
+      weekdays:
+        - Email
+      # Weekly run
+      mon:
+        - Run
+  - name: Work
+    duration: fill
+  - name: Lunch
+    start: noon
+    duration: 1h30m
+  - name: Walk
+    after: Lunch + 1h30m
+    duration: abc
+";
+        assert_eq!(format(PLAN), expected);
+    }
+
+    #[test]
+    fn formatting_is_idempotent() {
+        let once = format(PLAN);
+        assert_eq!(format(&once), once);
+    }
+}
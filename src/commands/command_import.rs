@@ -0,0 +1,110 @@
+use super::render_routines;
+use crate::internal::*;
+use chrono::{Datelike, Local};
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum ImportFormat {
+    /// comma-separated values with name, start, duration, day, and activity columns
+    Csv,
+}
+
+/// Builds a plan from a file, shows a preview of it, and writes it to the
+/// plan file, either replacing the current plan or merged into it.
+///
+pub fn command_import(
+    format: ImportFormat,
+    file: PathBuf,
+    merge: bool,
+    yes: bool,
+    width: Option<usize>,
+) -> Result<()> {
+    let app = App::new();
+    let text = std::fs::read_to_string(&file)
+        .map_err(|e| anyhow!("Could not read {}: {}", file.display(), e))?;
+    let imported = match format {
+        ImportFormat::Csv => Plan::from_csv(&text),
+    }
+    .map_err(|e| e.in_file(file.clone()))?;
+    if imported.daily_routine.is_empty() {
+        return Err(anyhow!("{} has no routines", file.display()));
+    }
+
+    let filename = app.find_data_filename()?;
+    let doc = if merge {
+        let mut doc = app.load_plan_document()?;
+        doc.merge(&imported);
+        doc
+    } else {
+        PlanDocument::from_plan(&imported)
+    };
+    let plan = doc.plan();
+    plan.validate()?;
+
+    let day = DayOfWeek::from_weekday(Local::now().weekday());
+    println!();
+    cprintln!(
+        "heading",
+        "Preview of the {} plan for {}",
+        if merge { "merged" } else { "imported" },
+        day.pretty()
+    );
+    render_routines(
+        &plan,
+        day.clone(),
+        None,
+        &[],
+        width.unwrap_or_else(terminal_width),
+    )?;
+    println!();
+
+    // Don't write a plan that doesn't work out.  Only the activities differ
+    // between days, so checking one day's schedule is enough.
+    let problems = Schedule::resolve(&plan, day)?.problems();
+    if !problems.is_empty() {
+        for problem in &problems {
+            cprintln!("warn", "⚠ {}", problem);
+        }
+        return Err(anyhow!(
+            "The {} plan has conflicts, so it wasn't written",
+            if merge { "merged" } else { "imported" }
+        ));
+    }
+
+    if !merge && filename.exists() {
+        cprintln!(
+            "warn",
+            "This replaces the current plan. Use \\-\\-merge to add to it instead."
+        );
+    }
+    if !yes
+        && !confirm(&format!(
+            "Write the plan to {}?",
+            app.guidebook_root_pretty()?
+        ))?
+    {
+        cprintln!("txt", "No changes made.");
+        return Ok(());
+    }
+
+    if let Some(dir) = filename.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    app.save_plan_document(&doc)?;
+    cprintln!(
+        "success",
+        "✓ Imported {} routines from [{}](filename)",
+        imported.daily_routine.len(),
+        file.display()
+    );
+    Ok(())
+}
+
+fn confirm(question: &str) -> Result<bool> {
+    cprint!("txt", "{} [y/N] ", question);
+    io::stdout().flush()?;
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    Ok(matches!(input.trim().to_lowercase().as_str(), "y" | "yes"))
+}
@@ -1,4 +1,5 @@
 pub use crate::internal::*;
+use guidebook_plan::templates::Template;
 use std::fs;
 use std::io::{self, Write};
 
@@ -6,8 +7,17 @@ use std::io::{self, Write};
 /// user to clone an existing remote repo or create a remote repo all
 /// from the command-line.
 ///
-pub async fn command_init() -> Result<()> {
+/// The initial plan is created from the given template, or from the
+/// "default" template if none is given.  Giving a template when the plan
+/// file already exists is an error, as it would not be applied.
+///
+pub async fn command_init(template: Option<String>) -> Result<()> {
     let mut app = App::new();
+    // Resolve the template up-front so a typo doesn't fail after the
+    // repository has already been created
+    let requested = template.clone();
+    let template = Template::resolve(template.as_deref().unwrap_or("default"))?;
+
     // Step 1: Check if guidebook root directory exists with .git subfolder
     let home = dirs::home_dir().ok_or_else(|| anyhow!("HOME directory not found"))?;
     let guidebook_root = home.join(".local/share/guidebook");
@@ -17,6 +27,18 @@ pub async fn command_init() -> Result<()> {
     // Step 1: Check if we're already all set...
     //
     if guidebook_root.exists() && git_dir.exists() {
+        let plan_file = guidebook_root.join(PLAN_RELATIVE_PATH);
+        if !plan_file.exists() {
+            return write_plan(&guidebook_root, &template);
+        }
+        if let Some(requested) = requested {
+            return Err(anyhow!(
+                "{} already exists, so the '{}' template was not applied. Move it aside first to start over from the template.",
+                plan_file.display(),
+                requested
+            ));
+        }
+
         let root = guidebook_root.display();
         let (git_status, git_status_color) = match app.git_status() {
             Ok(status) if status.is_empty() => ("working tree clean", "success_dim"),
@@ -93,7 +115,6 @@ Would you like to:
                     );
                 }
                 Err(e) => {
-                    cprintln!("error", "Failed to create repository: {}", e);
                     cprintln!(
                         "warn",
                         r#"
@@ -102,7 +123,7 @@ manually create a 'guidebook-local' repository on GitHub and then use the clone
 with the `guidebook-plan clone` command.
 "#
                     );
-                    return Ok(());
+                    return Err(e);
                 }
             }
         }
@@ -118,8 +139,13 @@ with the `guidebook-plan clone` command.
     //
     // Step 4: At this point, the guidebook root exists and is a git repository
     //
-    let plan_dir = guidebook_root.join("guidebook-plan");
-    let plan_file = plan_dir.join("plan.yaml");
+    write_plan(&guidebook_root, &template)
+}
+
+/// Writes the initial plan.yaml from the template unless one already exists.
+/// The file starts with a modeline pointing editors at the plan schema.
+fn write_plan(guidebook_root: &std::path::Path, template: &Template) -> Result<()> {
+    let plan_file = guidebook_root.join(PLAN_RELATIVE_PATH);
 
     if plan_file.exists() {
         cprintln!(
@@ -129,105 +155,27 @@ with the `guidebook-plan clone` command.
         return Ok(());
     }
 
-    // Create the default plan.yaml file
-    cprintln!("", "Creating default plan.yaml file...");
-    if !plan_dir.exists() {
-        fs::create_dir_all(&plan_dir)?;
+    cprintln!(
+        "",
+        "Creating plan.yaml from the [{}](key) template...",
+        template.name
+    );
+    if let Some(plan_dir) = plan_file.parent() {
+        fs::create_dir_all(plan_dir)?;
     }
-    let default_plan = create_default_plan();
-    let yaml_content = serde_yaml::to_string(&default_plan)?;
-    fs::write(&plan_file, yaml_content)?;
+    // Point YAML language servers at the schema so editors can check the plan
+    let text = format!("{}\n{}", plan_schema_modeline(), template.text);
+    fs::write(&plan_file, text)?;
 
-    cprintln!("success_dim", "✓ Created default plan");
+    cprintln!("success_dim", "✓ Created plan");
     cprintln!("success_dim", "✓ guidebook-plan is now ready to go!");
 
     Ok(())
 }
 
-fn create_default_plan() -> Plan {
-    use std::collections::HashMap;
-
-    let mut activities_map = HashMap::new();
-    activities_map.insert(DayOfWeek::Default, vec!["Default activity".to_string()]);
-
-    let routines = vec![
-        Routine {
-            name: "Wake".to_string(),
-            start: Some("7:00 am".to_string()),
-            duration: "30m".to_string(),
-            activities: {
-                let mut map = HashMap::new();
-                map.insert(DayOfWeek::Default, vec!["Get up and get ready".to_string()]);
-                map
-            },
-        },
-        Routine {
-            name: "Work".to_string(),
-            start: Some("9:00 am".to_string()),
-            duration: "3h".to_string(),
-            activities: {
-                let mut map = HashMap::new();
-                map.insert(DayOfWeek::Default, vec!["Morning work session".to_string()]);
-                map
-            },
-        },
-        Routine {
-            name: "Lunch".to_string(),
-            start: Some("12:00 pm".to_string()),
-            duration: "1h".to_string(),
-            activities: {
-                let mut map = HashMap::new();
-                map.insert(DayOfWeek::Default, vec!["Lunch break and rest".to_string()]);
-                map
-            },
-        },
-        Routine {
-            name: "Work".to_string(),
-            start: Some("1:00 pm".to_string()),
-            duration: "4h".to_string(),
-            activities: {
-                let mut map = HashMap::new();
-                map.insert(
-                    DayOfWeek::Default,
-                    vec!["Afternoon work session".to_string()],
-                );
-                map
-            },
-        },
-        Routine {
-            name: "Evening".to_string(),
-            start: Some("6:00 pm".to_string()),
-            duration: "3h".to_string(),
-            activities: {
-                let mut map = HashMap::new();
-                map.insert(
-                    DayOfWeek::Default,
-                    vec!["Evening activities and relaxation".to_string()],
-                );
-                map
-            },
-        },
-        Routine {
-            name: "Sleep".to_string(),
-            start: Some("10:00 pm".to_string()),
-            duration: "9h".to_string(),
-            activities: {
-                let mut map = HashMap::new();
-                map.insert(DayOfWeek::Default, vec!["Rest and sleep".to_string()]);
-                map
-            },
-        },
-    ];
-
-    Plan {
-        daily_routine: routines,
-    }
-}
-
 async fn create_github_repo(guidebook_root: &std::path::Path, repo_name: &str) -> Result<()> {
     use reqwest::Client;
     use serde_json::{json, Value};
-    use std::process::Command;
     use std::time::Duration;
 
     // Note: the client_id is safe to share publicly as it is not a secret.
@@ -248,11 +196,11 @@ async fn create_github_repo(guidebook_root: &std::path::Path, repo_name: &str) -
     if !device_response.status().is_success() {
         let status = device_response.status();
         let error_text = device_response.text().await?;
-        return Err(anyhow!(
+        return Err(Error::Network(format!(
             "Failed to initiate device flow: {}. Error: {}",
-            status,
-            error_text
-        ));
+            status, error_text
+        ))
+        .into());
     }
 
     let device_data: Value = device_response.json().await?;
@@ -276,7 +224,7 @@ async fn create_github_repo(guidebook_root: &std::path::Path, repo_name: &str) -
         verification_uri
     );
     cprintln!("", "");
-    cprintln!("", "And enter the code: [{}](#4CF)", user_code);
+    cprintln!("", "And enter the code: [{}](key)", user_code);
     cprintln!("", "");
     cprintln!("", "Waiting for authorization...");
 
@@ -312,14 +260,19 @@ async fn create_github_repo(guidebook_root: &std::path::Path, repo_name: &str) -
                     tokio::time::sleep(Duration::from_secs(5)).await;
                     continue;
                 }
-                "expired_token" => return Err(anyhow!("Device code expired. Please try again.")),
-                "access_denied" => return Err(anyhow!("User denied authorization.")),
-                _ => return Err(anyhow!("OAuth error: {}", error)),
+                "expired_token" => {
+                    return Err(Error::Auth("Device code expired. Please try again.".into()).into())
+                }
+                "access_denied" => {
+                    return Err(Error::Auth("User denied authorization.".into()).into())
+                }
+                _ => return Err(Error::Auth(format!("OAuth error: {}", error)).into()),
             }
         }
     }
 
-    let access_token = access_token.ok_or_else(|| anyhow!("Failed to get access token"))?;
+    let access_token = access_token
+        .ok_or_else(|| Error::Auth("Timed out waiting for authorization".to_string()))?;
     cprintln!("success", "✓ Successfully authorized!");
 
     // Step 4: Get user information to construct repository URL
@@ -331,10 +284,11 @@ async fn create_github_repo(guidebook_root: &std::path::Path, repo_name: &str) -
         .await?;
 
     if !user_response.status().is_success() {
-        return Err(anyhow!(
+        return Err(Error::Auth(format!(
             "Failed to get user info: {}",
             user_response.status()
-        ));
+        ))
+        .into());
     }
 
     let user_data: Value = user_response.json().await?;
@@ -342,7 +296,7 @@ async fn create_github_repo(guidebook_root: &std::path::Path, repo_name: &str) -
         .as_str()
         .ok_or_else(|| anyhow!("Missing username in user response"))?;
 
-    cprintln!("", "Creating repository for user: [{}](#4CF)", username);
+    cprintln!("", "Creating repository for user: [{}](key)", username);
 
     // Step 5: Create the repository
     let repo_response = client
@@ -359,7 +313,7 @@ async fn create_github_repo(guidebook_root: &std::path::Path, repo_name: &str) -
 
     if !repo_response.status().is_success() {
         let error_text = repo_response.text().await?;
-        return Err(anyhow!("Failed to create repository: {}", error_text));
+        return Err(Error::Network(format!("Failed to create repository: {}", error_text)).into());
     }
     cprintln!("success", "✓ Created private repository '{}'", repo_name);
 
@@ -375,16 +329,7 @@ async fn create_github_repo(guidebook_root: &std::path::Path, repo_name: &str) -
         std::fs::create_dir_all(parent)?;
     }
 
-    let output = Command::new("git")
-        .args(&["clone", &repo_url, &guidebook_root.to_string_lossy()])
-        .output()?;
-
-    if !output.status.success() {
-        return Err(anyhow!(
-            "Failed to clone repository: {}",
-            String::from_utf8_lossy(&output.stderr)
-        ));
-    }
+    guidebook_plan::repo::clone(&repo_url, guidebook_root, false)?;
     cprintln!("success", "✓ Repository cloned successfully");
 
     Ok(())
@@ -5,9 +5,14 @@ use chrono::Local;
 ///
 pub fn command_push() -> Result<()> {
     let mut app = App::new();
-    let git_status = app.run_git_cmd(&["status", "--porcelain"])?;
+    let git_status = app.git_status()?;
 
-    if !git_status.is_empty() {
+    // Only the plan is committed, so changes to other files don't count.
+    // git lists an untracked directory rather than the files in it.
+    let plan_changed = git_status.iter().any(|path| {
+        path == PLAN_RELATIVE_PATH || (path.ends_with('/') && PLAN_RELATIVE_PATH.starts_with(path))
+    });
+    if plan_changed {
         let filename = app.find_data_filename()?;
         let home = dirs::home_dir().unwrap();
         let pretty_filename = filename
@@ -17,17 +22,18 @@ pub fn command_push() -> Result<()> {
         let now = Local::now();
         let formatted_date = now.format("%Y-%m-%d %H:%M").to_string();
 
-        cprintln!("#39F", "Pushing changes to git: {}", pretty_filename);
+        cprintln!(
+            "txt",
+            "Pushing changes to git: [{}](filename)",
+            pretty_filename
+        );
 
-        app.run_git_cmd(&["add", &filename.to_string_lossy()])?;
-        app.run_git_cmd(&[
-            "commit",
-            "-m",
-            &format!("Update guidebook plan ({})", formatted_date),
-        ])?;
-        app.run_git_cmd(&["push"])?;
+        let repo = app.repo()?;
+        repo.add(PLAN_RELATIVE_PATH)?;
+        repo.commit(&format!("Update guidebook plan ({})", formatted_date))?;
+        repo.push()?;
     } else {
-        cprintln!("#3F9", "No changes to push.");
+        cprintln!("success", "No changes to push.");
     }
 
     Ok(())
@@ -0,0 +1,197 @@
+use crate::internal::*;
+
+#[derive(clap::Subcommand)]
+pub enum RoutineCommand {
+    /// add a new routine
+    Add {
+        name: String,
+        /// duration of the routine (e.g. 30m, 1h30m, 90min, or fill)
+        #[arg(long)]
+        duration: Option<String>,
+        /// fixed start time (e.g. 9:00 am, 13:00, noon)
+        #[arg(long)]
+        start: Option<String>,
+        /// start relative to another routine (e.g. "Lunch + 15m")
+        #[arg(long)]
+        after: Option<String>,
+        /// end time, as an alternative to the duration
+        #[arg(long)]
+        end: Option<String>,
+        /// 1-based position to insert at (defaults to the end)
+        #[arg(long)]
+        position: Option<usize>,
+    },
+    /// remove a routine
+    Remove {
+        /// routine name or 1-based position
+        routine: String,
+    },
+    /// move a routine to a new 1-based position
+    Move { routine: String, position: usize },
+    /// rename a routine
+    Rename { routine: String, name: String },
+    /// set the fixed start time of a routine ("none" to clear it)
+    SetStart { routine: String, start: String },
+    /// anchor a routine after another, e.g. "Lunch + 15m" ("none" to clear it)
+    SetAfter { routine: String, after: String },
+    /// set the end time of a routine ("none" to clear it)
+    SetEnd { routine: String, end: String },
+    /// set the duration of a routine ("none" to clear it)
+    SetDuration { routine: String, duration: String },
+}
+
+/// Edits the routines in the plan without needing to open an editor.
+///
+pub fn command_routine(command: RoutineCommand) -> Result<()> {
+    let app = App::new();
+    let mut doc = app.load_plan_document()?;
+    let plan = doc.plan();
+
+    let message = match command {
+        RoutineCommand::Add {
+            name,
+            duration,
+            start,
+            after,
+            end,
+            position,
+        } => {
+            let routine = Routine::new(Routine {
+                name,
+                start,
+                after,
+                end,
+                duration,
+                activities: HashMap::new(),
+            });
+            routine.validate()?;
+
+            let count = plan.daily_routine.len();
+            let index = match position {
+                Some(p) if p == 0 || p > count + 1 => {
+                    return Err(anyhow!("Position {} is out of range (1-{})", p, count + 1));
+                }
+                Some(p) => p - 1,
+                None => count,
+            };
+            let message = format!("Added routine [{}](key)", routine.name);
+            doc.insert_routine(index, routine);
+            message
+        }
+        RoutineCommand::Remove { routine } => {
+            let index = plan.find_routine(&routine)?;
+            let name = &plan.daily_routine[index].name;
+            let dependants = plan.anchored_after(name);
+            if !dependants.is_empty() {
+                let names: Vec<&str> = dependants
+                    .iter()
+                    .map(|&i| plan.daily_routine[i].name.as_str())
+                    .collect();
+                let (verb, pronoun) = match names.len() {
+                    1 => ("is", "it"),
+                    _ => ("are", "them"),
+                };
+                return Err(anyhow!(
+                    "Can't remove '{}' as {} {} anchored after it; use 'routine set-after' to move {} first",
+                    name,
+                    names.join(", "),
+                    verb,
+                    pronoun
+                ));
+            }
+            let removed = doc.remove_routine(index);
+            format!("Removed routine [{}](key)", removed.name)
+        }
+        RoutineCommand::Move { routine, position } => {
+            let index = plan.find_routine(&routine)?;
+            if position == 0 || position > plan.daily_routine.len() {
+                return Err(anyhow!(
+                    "Position {} is out of range (1-{})",
+                    position,
+                    plan.daily_routine.len()
+                ));
+            }
+            doc.move_routine(index, position - 1);
+            format!(
+                "Moved routine [{}](key) to position {}",
+                plan.daily_routine[index].name, position
+            )
+        }
+        RoutineCommand::Rename { routine, name } => {
+            let index = plan.find_routine(&routine)?;
+            let old = plan.daily_routine[index].name.clone();
+            // Keep the routines anchored after it pointing at it
+            let dependants = plan.anchored_after(&old);
+            for &i in &dependants {
+                doc.routine_mut(i).rename_anchor(&old, &name);
+            }
+            doc.routine_mut(index).name = name.clone();
+            let mut message = format!("Renamed routine [{}](key) to [{}](key)", old, name);
+            if !dependants.is_empty() {
+                message.push_str(&format!(
+                    " and updated the anchors of {} routine(s)",
+                    dependants.len()
+                ));
+            }
+            message
+        }
+        RoutineCommand::SetStart { routine, start } => {
+            let index = plan.find_routine(&routine)?;
+            let entry = doc.routine_mut(index);
+            entry.start = optional_value(&start).map(|s| s.to_lowercase());
+            entry.validate()?;
+            format!(
+                "Set start of [{}](key) to {}",
+                entry.name,
+                entry.start.as_deref().unwrap_or("none")
+            )
+        }
+        RoutineCommand::SetAfter { routine, after } => {
+            let index = plan.find_routine(&routine)?;
+            let entry = doc.routine_mut(index);
+            entry.after = optional_value(&after);
+            entry.validate()?;
+            entry.after_anchor()?;
+            format!(
+                "Anchored [{}](key) after {}",
+                entry.name,
+                entry.after.as_deref().unwrap_or("none")
+            )
+        }
+        RoutineCommand::SetEnd { routine, end } => {
+            let index = plan.find_routine(&routine)?;
+            let entry = doc.routine_mut(index);
+            entry.end = optional_value(&end).map(|s| s.to_lowercase());
+            entry.validate()?;
+            format!(
+                "Set end of [{}](key) to {}",
+                entry.name,
+                entry.end.as_deref().unwrap_or("none")
+            )
+        }
+        RoutineCommand::SetDuration { routine, duration } => {
+            let index = plan.find_routine(&routine)?;
+            let entry = doc.routine_mut(index);
+            entry.duration = optional_value(&duration);
+            entry.validate()?;
+            format!(
+                "Set duration of [{}](key) to {}",
+                entry.name,
+                entry.duration.as_deref().unwrap_or("none")
+            )
+        }
+    };
+
+    app.save_plan_document(&doc)?;
+    cprintln!("success", "✓ {}", message);
+    Ok(())
+}
+
+/// Treats "none" (or an empty string) as clearing an optional field.
+fn optional_value(value: &str) -> Option<String> {
+    match value.trim() {
+        "" => None,
+        v if v.eq_ignore_ascii_case("none") => None,
+        v => Some(v.to_string()),
+    }
+}
@@ -0,0 +1,22 @@
+use crate::internal::*;
+use std::path::PathBuf;
+
+/// Writes the JSON Schema for plan.yaml to a file, or to stdout if no file
+/// is given, e.g. for an editor's YAML language server to validate against.
+///
+pub fn command_schema(output: Option<PathBuf>) -> Result<()> {
+    let text = format!("{}\n", serde_json::to_string_pretty(&Plan::json_schema())?);
+    match output {
+        Some(filename) => {
+            std::fs::write(&filename, text)?;
+            cprintln!(
+                "success",
+                "✓ Wrote the plan schema (format v{}) to [{}](filename)",
+                PLAN_FORMAT_VERSION,
+                filename.display()
+            );
+        }
+        None => print!("{}", text),
+    }
+    Ok(())
+}
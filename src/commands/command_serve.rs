@@ -0,0 +1,33 @@
+use crate::internal::*;
+use crate::server::ServerConfig;
+use axum::http::HeaderValue;
+
+/// Serves the plan as JSON on localhost for dashboards and widgets.  Pages
+/// from `allow_origin`, e.g. a widget served elsewhere, may read it too.
+///
+pub async fn command_serve(port: u16, allow_origin: Option<String>) -> Result<()> {
+    let allow_origin = allow_origin.as_deref().map(parse_origin).transpose()?;
+    crate::server::serve(ServerConfig {
+        home: None,
+        port,
+        allow_origin,
+    })
+    .await
+}
+
+/// Checks that the text is an origin such as "http://localhost:3000": a
+/// scheme, host, and optional port, without a path.
+fn parse_origin(text: &str) -> Result<HeaderValue> {
+    let text = text.trim_end_matches('/');
+    let origin = url::Url::parse(text)
+        .ok()
+        .map(|url| url.origin().ascii_serialization())
+        .filter(|origin| origin == text)
+        .ok_or_else(|| {
+            anyhow!(
+                "'{}' isn't an origin; use a scheme and host such as http://localhost:3000",
+                text
+            )
+        })?;
+    Ok(HeaderValue::from_str(&origin)?)
+}
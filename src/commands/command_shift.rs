@@ -0,0 +1,84 @@
+use crate::internal::*;
+
+use chrono::Local;
+
+/// Pushes the rest of today's schedule later (or earlier) without touching
+/// the plan itself.  Shifts expire at the end of the day.
+///
+pub fn command_shift(
+    amount: Option<String>,
+    from: Option<String>,
+    compress: bool,
+    clear: bool,
+) -> Result<()> {
+    let app = App::new();
+    let today = Local::now().date_naive();
+    let mut shifts = app.load_shifts(today)?;
+
+    if clear {
+        shifts.shifts.clear();
+        app.save_shifts(&shifts)?;
+        cprintln!("success", "✓ Cleared today's shifts");
+        return Ok(());
+    }
+
+    let Some(amount) = amount else {
+        if shifts.shifts.is_empty() {
+            cprintln!("txt", "No shifts today");
+        }
+        for shift in &shifts.shifts {
+            cprintln!(
+                "txt",
+                "[{}](key) from [{}](key){}",
+                format_shift_amount(shift.minutes),
+                shift.name,
+                if shift.compress { " (compressed)" } else { "" }
+            );
+        }
+        return Ok(());
+    };
+    let minutes = parse_shift_amount(&amount)?;
+
+    let plan = app.load_plan()?;
+    let index = match from {
+        Some(from) => plan.find_routine(&from)?,
+        None => next_routine_index(&plan, &shifts)?,
+    };
+    let name = plan.daily_routine[index].name.clone();
+
+    shifts.shifts.push(Shift {
+        index,
+        name: name.clone(),
+        minutes,
+        compress,
+    });
+    app.save_shifts(&shifts)?;
+
+    cprintln!(
+        "success",
+        "✓ Shifted [{}](key) and later routines by {} for today",
+        name,
+        format_shift_amount(minutes)
+    );
+    Ok(())
+}
+
+/// The routine after the one in progress, or the next one to start if
+/// nothing is in progress.
+fn next_routine_index(plan: &Plan, shifts: &DayShifts) -> Result<usize> {
+    let mut schedule = Schedule::resolve(plan, plan.current_day_of_week())?;
+    for shift in &shifts.shifts {
+        schedule.apply_shift(shift);
+    }
+
+    let now = plan.current_time_mins();
+    let next = match schedule.index_at(now) {
+        Some(current) => schedule.blocks.get(current + 1),
+        None => schedule
+            .blocks
+            .iter()
+            .find(|b| b.start > now && b.start < now + 24 * 60),
+    };
+    next.map(|b| b.index)
+        .ok_or_else(|| anyhow!("Nothing left to shift today; use --from to pick a routine"))
+}
@@ -1,10 +1,31 @@
 use crate::internal::*;
 
 use anyhow::Result;
+use chrono::{Datelike, Local, NaiveDate};
+use std::sync::OnceLock;
 
-pub fn command_show() -> Result<()> {
-    let plan = load_plan()?;
+/// Below this width `show` switches to a compact layout.
+const COMPACT_WIDTH: usize = 50;
+
+pub fn command_show(
+    day: Option<DaySelector>,
+    as_of: Option<NaiveDate>,
+    width: Option<usize>,
+) -> Result<()> {
+    let today = Local::now().date_naive();
+    let width = width.unwrap_or_else(terminal_width);
+    match (day, as_of) {
+        (None, Some(date)) => show_as_of(date, None, width),
+        (Some(day), Some(date)) => show_as_of(date, Some(day.resolve().0), width),
+        (None, None) => show_today(width),
+        (Some(DaySelector::Date(date)), None) if date == today => show_today(width),
+        (Some(day), None) => show_day(day, width),
+    }
+}
+
+fn show_today(width: usize) -> Result<()> {
     let mut app = App::new();
+    let plan = app.load_plan()?;
 
     let day_of_week = plan.current_day_of_week();
     let current_mins = plan.current_time_mins();
@@ -14,88 +35,503 @@ pub fn command_show() -> Result<()> {
 
     println!();
     cprintln!(
-        "#FC1",
+        "heading",
         "{} {}",
         plan.current_day_of_week_pretty(),
         plan.current_time_pretty()
     );
-    cprintln!("#531", "filename:   [{}](#531)", pretty_filename);
+    cprintln!("info", "filename:   [{}](info)", pretty_filename);
 
     if !git_status.is_empty() {
-        cprintln!("#F90", "git status: [modified](#F90)");
+        cprintln!("modified", "git status: [modified](modified)");
     } else {
-        cprintln!("#531", "git status: up to date");
+        cprintln!("info", "git status: up to date");
     }
 
-    cprintln!("#555", "{}", "-".repeat(70));
+    let shifts = app.load_shifts(Local::now().date_naive())?;
+    if !shifts.shifts.is_empty() {
+        let total: i32 = shifts.shifts.iter().map(|s| s.minutes).sum();
+        cprintln!(
+            "info",
+            "shifted:    [{}](shifted) today ([guidebook-plan shift \\-\\-clear](info) to undo)",
+            format_shift_amount(total)
+        );
+    }
 
-    let current_index = plan.current_activity_index()?;
+    render_routines(
+        &plan,
+        day_of_week,
+        Some(current_mins),
+        &shifts.shifts,
+        width,
+    )
+}
+
+/// Shows the plan for a day other than today, e.g. to plan ahead the
+/// evening before.  There's no current routine to highlight.
+fn show_day(day: DaySelector, width: usize) -> Result<()> {
+    let app = App::new();
+    let plan = app.load_plan()?;
+    let (day_of_week, date) = day.resolve();
 
-    if plan.daily_routine.is_empty() {
-        return Ok(());
+    println!();
+    match date {
+        Some(date) => cprintln!("heading", "{} {}", day_of_week.pretty(), date),
+        None => cprintln!("heading", "{}", day_of_week.pretty()),
     }
+    cprintln!(
+        "info",
+        "filename:   [{}](info)",
+        app.guidebook_root_pretty()?
+    );
 
-    let first_start = plan.daily_routine[0].start_minutes()?.unwrap_or(0);
-    let mut cursor = TimeCursor::new(first_start);
+    render_routines(&plan, day_of_week, None, &[], width)
+}
 
-    for (index, routine) in plan.daily_routine.iter().enumerate() {
-        let dist = (current_index - index as i32).abs();
+/// Shows the plan as it was committed on the given date, for that date's
+/// day of the week unless another day is given.
+fn show_as_of(date: NaiveDate, day: Option<DayOfWeek>, width: usize) -> Result<()> {
+    let mut app = App::new();
+    let Some((rev, rev_date)) = app.plan_revision_as_of(date)? else {
+        return Err(anyhow!(
+            "no revision of {} found on or before {}",
+            PLAN_RELATIVE_PATH,
+            date
+        ));
+    };
+    let plan = app.load_plan_at(&rev)?;
+    let day_of_week = day.unwrap_or_else(|| DayOfWeek::from_weekday(date.weekday()));
 
-        if let Some(activity_start) = routine.start_minutes()? {
-            cursor.set(activity_start);
-        }
+    println!();
+    cprintln!("heading", "{} {}", day_of_week.pretty(), date);
+    cprintln!("info", "revision:   [{}](info) ({})", rev, rev_date);
 
-        let activities = routine.activities(day_of_week.clone());
+    render_routines(&plan, day_of_week, None, &[], width)
+}
+
+/// Renders the routines of the plan for the given day.  When `current_mins`
+/// is provided, the routine in progress at that time is highlighted.  Any
+/// shifts are applied to the schedule and the routines they moved are marked.
+///
+/// The output is laid out to fit `width` columns, switching to a compact
+/// layout for narrow terminals.
+pub fn render_routines(
+    plan: &Plan,
+    day_of_week: DayOfWeek,
+    current_mins: Option<u32>,
+    shifts: &[Shift],
+    width: usize,
+) -> Result<()> {
+    for (color, line) in routine_lines(plan, day_of_week, current_mins, shifts, width)? {
+        cprintln!(color, "{}", line);
+    }
+    Ok(())
+}
+
+/// The lines printed by [`render_routines`], each with its cprintln color.
+fn routine_lines(
+    plan: &Plan,
+    day_of_week: DayOfWeek,
+    current_mins: Option<u32>,
+    shifts: &[Shift],
+    width: usize,
+) -> Result<Vec<(&'static str, String)>> {
+    let compact = width < COMPACT_WIDTH;
+    let (indent, hanging) = if compact { (2, 4) } else { (8, 10) };
+
+    let mut lines = Vec::new();
+    lines.push(("rule", rule(width)));
+
+    let mut schedule = Schedule::resolve(plan, day_of_week)?;
+    for shift in shifts {
+        schedule.apply_shift(shift);
+    }
+    let current_index = current_mins.and_then(|mins| schedule.index_at(mins));
 
-        let (c0, c1, c2, c3) = if dist == 0 {
-            ("#555", "#738", "#55C", "#8DF")
+    for (index, block) in schedule.blocks.iter().enumerate() {
+        let is_current = current_index == Some(index);
+        let cursor = TimeCursor::new(block.start);
+
+        match schedule.gap_before(index) {
+            gap if gap > 0 => lines.push(free_time_line(
+                block.start - gap as u32,
+                gap as u32,
+                compact,
+            )),
+            gap if gap < 0 => lines.push((
+                "warn",
+                format!(
+                    "  ⚠ [{}](key) overlaps [{}](key) by {}",
+                    block.name,
+                    schedule.blocks[index - 1].name,
+                    format_duration(-gap as f64)
+                ),
+            )),
+            _ => {}
+        }
+
+        let (c0, c1, c2, c3) = if is_current {
+            (
+                "current_block",
+                "current_bullet",
+                "current_time",
+                "current_name",
+            )
         } else {
-            ("#444", "#555", "#666", "#69B")
+            ("block", "block_bullet", "block_time", "block_name")
         };
 
-        if dist == 0 {
-            cprintln!("#FC1", "{}", "-".repeat(70));
+        if is_current {
+            lines.push(("current_rule", rule(width)));
         }
 
-        let duration_text = if dist == 0 {
-            let remaining = cursor.minutes_remaining(current_mins, routine.duration()? as u32);
-            format!(" total, {}m left", remaining)
-        } else {
-            String::new()
+        let duration_text = match current_mins {
+            Some(current_mins) if is_current => {
+                // The block may have started before midnight
+                let now = if current_mins < block.start {
+                    current_mins + 24 * 60
+                } else {
+                    current_mins
+                };
+                let remaining = cursor.minutes_remaining(now, block.duration);
+                match compact {
+                    true => format!(", {}m left", remaining),
+                    false => format!(" total, {}m left", remaining),
+                }
+            }
+            _ => String::new(),
         };
 
-        cprintln!(
-            c0,
-            "[▪]({}) [{}]({}) [{}]({}) ([{}{}]({}))",
-            c1,
-            cursor.pretty(),
-            c2,
-            routine.name(),
-            c3,
-            routine.pretty_duration()?,
-            duration_text,
-            c2
-        );
+        if compact {
+            lines.push((
+                c0,
+                format!(
+                    "[{}]({}) [{}]({}) [{}{}]({}){}",
+                    cursor.pretty(),
+                    c2,
+                    block.name,
+                    c3,
+                    format_duration(block.duration as f64),
+                    duration_text,
+                    c2,
+                    shift_note(block)
+                ),
+            ));
+        } else {
+            lines.push((
+                c0,
+                format!(
+                    "[▪]({}) [{}]({}) [{}]({}) ([{}{}]({})){}",
+                    c1,
+                    cursor.pretty(),
+                    c2,
+                    block.name,
+                    c3,
+                    format_duration(block.duration as f64),
+                    duration_text,
+                    c2,
+                    shift_note(block)
+                ),
+            ));
+        }
 
-        for activity in activities {
-            let color = if dist == 0 { "#788" } else { "#444" };
-            cprintln!(color, "        {}", activity);
+        for activity in &block.activities {
+            let color = if is_current {
+                "current_activity"
+            } else {
+                "activity"
+            };
+            let wrapped = wrap_markdown(activity, width.saturating_sub(hanging));
+            for (i, line) in wrapped.iter().enumerate() {
+                let pad = if i == 0 { indent } else { hanging };
+                lines.push((
+                    color,
+                    format!("{}{}", " ".repeat(pad), escape_leading(line)),
+                ));
+            }
         }
 
-        if dist == 0 {
-            cprintln!("#FC1", "{}", "-".repeat(70));
+        if is_current {
+            lines.push(("current_rule", rule(width)));
         }
+    }
 
-        cursor.add(routine.duration()? as u32);
+    if let Some(last) = schedule.blocks.last() {
+        let gap = schedule.gap_after_last();
+        if gap > 0 {
+            lines.push(free_time_line(last.end(), gap, compact));
+        }
     }
 
-    Ok(())
+    lines.push(("rule", rule(width)));
+    lines.push((
+        "summary",
+        format!(
+            "planned [{}](summary_value), unplanned [{}](summary_value)",
+            format_duration(schedule.planned_minutes() as f64),
+            format_duration(schedule.unplanned_minutes() as f64)
+        ),
+    ));
+
+    if !schedule.conflicts.is_empty() {
+        lines.push(("txt", String::new()));
+        for conflict in &schedule.conflicts {
+            lines.push(("warn", format!("⚠ {}", conflict.message)));
+        }
+    }
+
+    Ok(lines)
 }
 
-fn load_plan() -> Result<Plan> {
-    let app = App::new();
-    let filename = app.find_data_filename()?;
-    let text = std::fs::read_to_string(filename)?;
-    let data: Plan = serde_yaml::from_str(&text)?;
-    Ok(Plan::new(data))
+fn free_time_line(start: u32, minutes: u32, compact: bool) -> (&'static str, String) {
+    let start = TimeCursor::new(start).pretty();
+    let duration = format_duration(minutes as f64);
+    if compact {
+        (
+            "free_time_dim",
+            format!(
+                "[{}](block_bullet) [free](free_time) [{}](block_bullet)",
+                start, duration
+            ),
+        )
+    } else {
+        (
+            "free_time_dim",
+            format!(
+                "[▫](free_time_dim) [{}](block_bullet) [free time](free_time) ([{}](block_bullet))",
+                start, duration
+            ),
+        )
+    }
+}
+
+/// Word-wraps text to the given width, measuring words by their visible
+/// text.  Markdown links, code, and styled phrases aren't broken up, so
+/// they're still formatted once split across lines, unless they're wider
+/// than a line; then each word is styled on its own.  Words longer than
+/// the width are split.
+fn wrap_markdown(text: &str, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    let protected = markdown_spans().replace_all(text, |caps: &regex::Captures| {
+        let span = &caps[0];
+        if visible_len(span) <= width {
+            return span.replace(' ', "\u{a0}");
+        }
+        let (open, inner, close) = span_parts(span);
+        inner
+            .split(' ')
+            .filter(|w| !w.is_empty())
+            .map(|w| format!("{}{}{}", open, w, close).replace(' ', "\u{a0}"))
+            .collect::<Vec<_>>()
+            .join(" ")
+    });
+
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    let mut line_len = 0;
+    for word in protected.split(' ').filter(|w| !w.is_empty()) {
+        let word = word.replace('\u{a0}', " ");
+        let mut pieces = split_word(&word, width);
+        let last = pieces.pop().unwrap_or_default();
+        if !pieces.is_empty() && line_len > 0 {
+            lines.push(std::mem::take(&mut line));
+            line_len = 0;
+        }
+        lines.extend(pieces);
+
+        let word_len = visible_len(&last);
+        if line_len > 0 && line_len + 1 + word_len > width {
+            lines.push(std::mem::take(&mut line));
+            line_len = 0;
+        }
+        if line_len > 0 {
+            line.push(' ');
+            line_len += 1;
+        }
+        line.push_str(&last);
+        line_len += word_len;
+    }
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+/// Links, code, bold, italic, and strikethrough spans.
+fn markdown_spans() -> &'static Regex {
+    static SPANS: OnceLock<Regex> = OnceLock::new();
+    SPANS.get_or_init(|| {
+        Regex::new(concat!(
+            r"\[[^\]]*\]\([^)]*\)|`[^`]*`|\*\*[^*]*\*\*|~~[^~]*~~",
+            r"|\*[^*\s](?:[^*]*[^*\s])?\*|\b_[^_]+_\b",
+        ))
+        .unwrap()
+    })
+}
+
+/// Splits a span matched by [`markdown_spans`] into its opening markup,
+/// text, and closing markup, e.g. `("[", "docs", "](https://...)")`.
+fn span_parts(span: &str) -> (&str, &str, &str) {
+    if span.starts_with('[') {
+        let end = span.find("](").unwrap_or(span.len() - 1);
+        return ("[", &span[1..end], &span[end..]);
+    }
+    let delim = ["**", "~~", "`", "*", "_"]
+        .into_iter()
+        .find(|d| span.starts_with(d))
+        .unwrap_or("");
+    (delim, &span[delim.len()..span.len() - delim.len()], delim)
+}
+
+/// The number of characters a piece of Markdown takes up on screen.
+fn visible_len(markdown: &str) -> usize {
+    cprintln_plain_text(markdown).chars().count()
+}
+
+/// Splits a word into pieces of at most `width` visible characters.  A word
+/// that is a single styled span keeps its style in every piece; any other
+/// word with markup is split as plain text.
+fn split_word(word: &str, width: usize) -> Vec<String> {
+    if visible_len(word) <= width {
+        return vec![word.to_string()];
+    }
+    let (open, inner, close) = match markdown_spans().find(word) {
+        Some(m) if m.as_str() == word => span_parts(word),
+        _ => ("", word, ""),
+    };
+    let plain = if visible_len(inner) == inner.chars().count() {
+        inner.to_string()
+    } else {
+        cprintln_plain_text(inner)
+    };
+    let chars: Vec<char> = plain.chars().collect();
+    chars
+        .chunks(width)
+        .map(|chunk| format!("{}{}{}", open, chunk.iter().collect::<String>(), close))
+        .collect()
+}
+
+/// Escapes a character at the start of a line that would otherwise be read
+/// as Markdown, e.g. a wrapped line starting with "- ".
+fn escape_leading(line: &str) -> String {
+    let mut chars = line.chars();
+    match (chars.next(), chars.next()) {
+        (Some('-' | '+' | '*' | '#'), Some(' ')) | (Some('>'), _) => format!("\\{}", line),
+        _ => line.to_string(),
+    }
+}
+
+/// Describes how today's shifts changed a block, e.g. " moved +20m".
+fn shift_note(block: &ScheduledBlock) -> String {
+    let mut notes = Vec::new();
+    if block.shifted != 0 {
+        notes.push(format!("moved {}", format_shift_amount(block.shifted)));
+    }
+    if block.resized != 0 {
+        notes.push(format!("length {}", format_shift_amount(block.resized)));
+    }
+    if notes.is_empty() {
+        return String::new();
+    }
+    format!(" [{}](shifted)", notes.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAN: &str = "\
+daily_routine:
+  - name: Wake
+    start: 7am
+    duration: 30m
+  - name: Work
+    start: 8am
+    duration: 2h
+  - name: Meeting
+    start: 9:30am
+    duration: 1h
+";
+
+    fn lines(width: usize) -> Vec<String> {
+        let plan = Plan::from_yaml(PLAN).unwrap();
+        routine_lines(&plan, DayOfWeek::Mon, None, &[], width)
+            .unwrap()
+            .into_iter()
+            .map(|(_, line)| cprintln_plain_text(&line))
+            .collect()
+    }
+
+    #[test]
+    fn shows_gaps_as_free_time() {
+        let wide = lines(80);
+        let work = wide.iter().position(|l| l.contains("Work")).unwrap();
+        assert_eq!(wide[work - 1], "▫ 07:30 free time (30m)");
+
+        let compact = lines(40);
+        let work = compact.iter().position(|l| l.contains("Work")).unwrap();
+        assert_eq!(compact[work - 1], "07:30 free 30m");
+    }
+
+    #[test]
+    fn warns_about_overlaps() {
+        let lines = lines(80);
+        let meeting = lines.iter().position(|l| l.contains("Meeting (")).unwrap();
+        assert_eq!(lines[meeting - 1], "⚠ Meeting overlaps Work by 30m");
+    }
+
+    #[test]
+    fn summarizes_planned_and_unplanned_time() {
+        let lines = lines(80);
+        assert_eq!(lines.last().unwrap(), "planned 3h, unplanned 21h");
+    }
+
+    #[test]
+    fn wraps_between_words() {
+        assert_eq!(
+            wrap_markdown("read the next chapter", 10),
+            vec!["read the", "next", "chapter"]
+        );
+        assert_eq!(wrap_markdown("", 10), vec![""]);
+    }
+
+    #[test]
+    fn keeps_spans_together() {
+        assert_eq!(
+            wrap_markdown("review [open PRs](https://example.com/pulls) today", 12),
+            vec!["review", "[open PRs](https://example.com/pulls)", "today"]
+        );
+        assert_eq!(
+            wrap_markdown("a *very quiet* walk", 11),
+            vec!["a", "*very quiet*", "walk"]
+        );
+        assert_eq!(
+            wrap_markdown("a _very quiet_ walk", 11),
+            vec!["a", "_very quiet_", "walk"]
+        );
+        assert_eq!(
+            wrap_markdown("fix snake_case_name", 30),
+            vec!["fix snake_case_name"]
+        );
+    }
+
+    #[test]
+    fn styles_each_word_of_a_wide_span() {
+        assert_eq!(
+            wrap_markdown("**finish the quarterly report**", 12),
+            vec!["**finish** **the**", "**quarterly**", "**report**"]
+        );
+    }
+
+    #[test]
+    fn splits_overlong_words() {
+        assert_eq!(
+            wrap_markdown("see abcdefghijklmnopqrstuvwxyz now", 10),
+            vec!["see", "abcdefghij", "klmnopqrst", "uvwxyz now"]
+        );
+        assert_eq!(
+            wrap_markdown("`abcdefghijkl`", 5),
+            vec!["`abcde`", "`fghij`", "`kl`"]
+        );
+    }
 }
@@ -0,0 +1,60 @@
+use super::render_routines;
+use crate::internal::*;
+use guidebook_plan::templates::Template;
+
+#[derive(clap::Subcommand)]
+pub enum TemplateCommand {
+    /// list the built-in templates
+    List,
+    /// preview a template
+    Show {
+        /// built-in template name, path, or git URL
+        template: String,
+        /// day of the week to preview (defaults to today)
+        #[arg(long)]
+        day: Option<DayOfWeek>,
+    },
+}
+
+/// Lists and previews the templates that can be used with `init --template`.
+///
+pub fn command_template(command: TemplateCommand) -> Result<()> {
+    match command {
+        TemplateCommand::List => {
+            cprintln!("h1", "Built-in templates:");
+            for template in Template::built_in() {
+                cprintln!(
+                    "txt",
+                    "    [{:<14}](key) {}",
+                    template.name,
+                    template.description
+                );
+            }
+            cprintln!("", "");
+            cprintln!(
+                "txt",
+                "Preview one with [guidebook-plan template show NAME](command)"
+            );
+        }
+        TemplateCommand::Show { template, day } => {
+            let template = Template::resolve(&template)?;
+            let plan = template.plan()?;
+            let day = day.unwrap_or_else(|| plan.current_day_of_week());
+
+            println!();
+            cprintln!("heading", "{} template, {}", template.name, day.pretty());
+            if !template.description.is_empty() {
+                cprintln!("info", "{}", template.description);
+            }
+            render_routines(&plan, day, None, &[], terminal_width())?;
+
+            cprintln!("", "");
+            cprintln!(
+                "txt",
+                "Use it with [guidebook-plan init --template {}](command)",
+                template.name
+            );
+        }
+    }
+    Ok(())
+}
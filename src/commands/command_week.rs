@@ -0,0 +1,233 @@
+use crate::internal::*;
+
+use crate::themes::routine_color;
+use chrono::Local;
+
+/// Each row of the grid covers this many minutes.
+const SLOT_MINUTES: u32 = 30;
+const TIME_WIDTH: usize = 6;
+/// Narrowest a day's column can be: two characters and a space.
+const MIN_COLUMN: usize = 3;
+
+/// Shows the plan for each day of the week side by side, with today's
+/// column and the current routine highlighted.
+///
+pub fn command_week(width: Option<usize>) -> Result<()> {
+    let app = App::new();
+    let plan = app.load_plan()?;
+    let today = plan.current_day_of_week();
+    let now = plan.current_time_mins();
+    let shifts = app.load_shifts(Local::now().date_naive())?;
+
+    let days = DayOfWeek::week();
+    let mut schedules = Vec::new();
+    for day in days {
+        let mut schedule = Schedule::resolve(&plan, day.clone())?;
+        if *day == today {
+            for shift in &shifts.shifts {
+                schedule.apply_shift(shift);
+            }
+        }
+        schedules.push(schedule);
+    }
+    if schedules.iter().all(|s| s.blocks.is_empty()) {
+        cprintln!("txt", "The plan has no routines");
+        return Ok(());
+    }
+
+    let width = width.unwrap_or_else(terminal_width);
+    println!();
+    for line in render_week(&schedules, &today, now, width)? {
+        cprintln!("rule", "{}", line);
+    }
+    Ok(())
+}
+
+/// Renders the grid for the week's schedules, starting on Monday, as
+/// cprintln lines no wider than `width`.
+fn render_week(
+    schedules: &[Schedule],
+    today: &DayOfWeek,
+    now: u32,
+    width: usize,
+) -> Result<Vec<String>> {
+    let days = DayOfWeek::week();
+    let column = width.saturating_sub(TIME_WIDTH) / days.len();
+    if column < MIN_COLUMN {
+        return Err(anyhow!(
+            "The week needs at least {} columns to show; use --width to set it",
+            TIME_WIDTH + MIN_COLUMN * days.len()
+        ));
+    }
+
+    let first = schedules
+        .iter()
+        .filter_map(|s| s.blocks.first())
+        .map(|b| b.start)
+        .min()
+        .unwrap_or(0);
+    let last = schedules
+        .iter()
+        .flat_map(|s| s.blocks.iter())
+        .map(|b| b.end())
+        .max()
+        .unwrap_or(first)
+        .min(first + 24 * 60);
+
+    let rule = "─".repeat(TIME_WIDTH + column * days.len());
+
+    let mut lines = Vec::new();
+    let mut header = " ".repeat(TIME_WIDTH);
+    for day in days {
+        let color = if day == today {
+            "week_today"
+        } else {
+            "week_day"
+        };
+        header += &format!("[{}]({})", fit_width(day.pretty(), column), color);
+    }
+    lines.push(header);
+    lines.push(rule.clone());
+
+    let mut slot = first - first % SLOT_MINUTES;
+    while slot < last {
+        let label = if slot.is_multiple_of(60) {
+            TimeCursor::new(slot).pretty()
+        } else {
+            String::new()
+        };
+        let mut line = format!("[{}](rule)", fit_width(&label, TIME_WIDTH));
+
+        for (day, schedule) in days.iter().zip(schedules) {
+            let current = match day == today {
+                true => schedule.index_at(now),
+                false => None,
+            };
+            line += &render_cell(schedule, slot, column, current);
+        }
+        lines.push(line);
+        slot += SLOT_MINUTES;
+    }
+    lines.push(rule);
+
+    Ok(lines)
+}
+
+/// Renders the part of a day's schedule that falls in the slot starting at
+/// `slot`: the routine's name on its first row, followed by its activities.
+fn render_cell(schedule: &Schedule, slot: u32, width: usize, current: Option<usize>) -> String {
+    let slot_end = slot + SLOT_MINUTES;
+
+    // Prefer a block starting in this slot over one that's still running
+    let index = schedule
+        .blocks
+        .iter()
+        .position(|b| b.start >= slot && b.start < slot_end && b.duration > 0)
+        .or_else(|| {
+            schedule
+                .blocks
+                .iter()
+                .rposition(|b| b.start < slot && b.end() > slot)
+        });
+    let Some(index) = index else {
+        return " ".repeat(width);
+    };
+    let block = &schedule.blocks[index];
+
+    let is_current = current == Some(index);
+    let name_color = match is_current {
+        true => "week_current",
+        false => routine_color(&block.name),
+    };
+
+    let row = (slot - (block.start - block.start % SLOT_MINUTES)) / SLOT_MINUTES;
+    let inner = width.saturating_sub(1);
+    match row {
+        0 => format!(
+            "[{}]({}) ",
+            fit_width(&cprintln_plain_text(&block.name), inner),
+            name_color
+        ),
+        row => {
+            let activity = block.activities.get(row as usize - 1);
+            let activity = activity.map(|a| cprintln_plain_text(a)).unwrap_or_default();
+            let text = format!("┊{}", activity);
+            let color = match is_current {
+                true => "week_current_activity",
+                false => "week_activity",
+            };
+            format!("[{}]({}) ", fit_width(&text, inner), color)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use unicode_width::UnicodeWidthStr;
+
+    const PLAN: &str = "\
+daily_routine:
+  - name: Wake
+    start: 7am
+    duration: 30m
+  - name: 会議
+    start: 9am
+    duration: 1h
+    activities:
+      default: [Email, Standup]
+";
+
+    fn schedules() -> Vec<Schedule> {
+        let plan = Plan::from_yaml(PLAN).unwrap();
+        DayOfWeek::week()
+            .iter()
+            .map(|day| Schedule::resolve(&plan, day.clone()).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn cells_fit_their_width() {
+        let schedule = &schedules()[0];
+        let color = routine_color("会議");
+        assert_eq!(
+            render_cell(schedule, 9 * 60, 10, None),
+            format!("[会議     ]({}) ", color)
+        );
+        assert_eq!(
+            render_cell(schedule, 9 * 60, 3, None),
+            format!("[… ]({}) ", color)
+        );
+        assert_eq!(
+            render_cell(schedule, 9 * 60 + 30, 10, Some(1)),
+            "[┊Email   ](week_current_activity) "
+        );
+        assert_eq!(
+            render_cell(schedule, 9 * 60 + 30, 5, None),
+            "[┊Em…](week_activity) "
+        );
+        assert_eq!(render_cell(schedule, 8 * 60, 5, None), "     ");
+    }
+
+    #[test]
+    fn week_fits_narrow_and_wide_terminals() {
+        let schedules = schedules();
+        for width in [27, 40, 80, 200] {
+            let lines = render_week(&schedules, &DayOfWeek::Mon, 0, width).unwrap();
+            // The header starts with spaces, which cprintln keeps as they are
+            for line in &lines[1..] {
+                let text = cprintln_plain_text(line);
+                assert!(text.width() <= width, "{:?} is wider than {}", text, width);
+            }
+        }
+
+        let lines = render_week(&schedules, &DayOfWeek::Mon, 0, 200).unwrap();
+        assert!(lines.iter().any(|line| line.contains("[┊Email")));
+    }
+
+    #[test]
+    fn week_needs_room_for_every_day() {
+        let error = render_week(&schedules(), &DayOfWeek::Mon, 0, 26).unwrap_err();
+        assert!(error.to_string().contains("at least 27 columns"));
+    }
+}
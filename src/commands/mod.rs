@@ -1,11 +1,33 @@
+mod command_activity;
 mod command_clone;
+mod command_diff;
+mod command_export;
+mod command_fmt;
+mod command_import;
 mod command_init;
 mod command_open;
 mod command_push;
+mod command_routine;
+mod command_schema;
+mod command_serve;
+mod command_shift;
 mod command_show;
+mod command_template;
+mod command_week;
 
+pub use command_activity::*;
 pub use command_clone::*;
+pub use command_diff::*;
+pub use command_export::*;
+pub use command_fmt::*;
+pub use command_import::*;
 pub use command_init::*;
 pub use command_open::*;
 pub use command_push::*;
+pub use command_routine::*;
+pub use command_schema::*;
+pub use command_serve::*;
+pub use command_shift::*;
 pub use command_show::*;
+pub use command_template::*;
+pub use command_week::*;
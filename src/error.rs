@@ -0,0 +1,132 @@
+//! The errors returned by the library.  Every fallible function returns
+//! [`Result`], so callers can match on the kind of failure, e.g. to exit
+//! with a particular status, without downcasting.
+//!
+use crate::model::TimeParseError;
+use std::path::PathBuf;
+
+/// The result of a fallible library function.
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Why a library function failed.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// The guidebook data directory doesn't exist
+    #[error("guidebook data directory not found at {}", .0.display())]
+    MissingRoot(PathBuf),
+
+    /// The data directory exists but has no plan file
+    #[error("plan file not found at {}", .0.display())]
+    MissingPlan(PathBuf),
+
+    /// The plan file isn't valid YAML or doesn't match the plan format
+    #[error("{}", format_parse_error(.file, *.line, *.column, .message))]
+    Parse {
+        /// The file being parsed, if known
+        file: Option<PathBuf>,
+        /// 1-based line of the problem
+        line: Option<usize>,
+        /// 1-based column of the problem
+        column: Option<usize>,
+        /// What's wrong, without the location
+        message: String,
+    },
+
+    /// A git command failed
+    #[error("git {command} failed: {stderr}")]
+    Git {
+        /// The git command or operation, e.g. "commit"
+        command: String,
+        /// What git reported
+        stderr: String,
+    },
+
+    /// A request to a remote service failed
+    #[error("network error: {0}")]
+    Network(String),
+
+    /// Authorization with a remote service was denied or expired
+    #[error("authorization failed: {0}")]
+    Auth(String),
+
+    /// A time of day or duration that can't be parsed
+    #[error(transparent)]
+    Time(#[from] TimeParseError),
+
+    /// Reading or writing a file failed
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    /// Anything else that's wrong with the input, e.g. a routine that
+    /// doesn't exist
+    #[error("{0}")]
+    Invalid(String),
+}
+
+impl From<serde_yaml::Error> for Error {
+    fn from(error: serde_yaml::Error) -> Self {
+        Error::from_yaml(&error)
+    }
+}
+
+/// Creates an [`Error::Invalid`] from a format string, like `anyhow!`.
+macro_rules! invalid {
+    ($($arg:tt)*) => {
+        $crate::Error::Invalid(format!($($arg)*))
+    };
+}
+pub(crate) use invalid;
+
+impl Error {
+    /// Converts a YAML error into a parse error with its location.
+    pub fn from_yaml(error: &serde_yaml::Error) -> Self {
+        let location = error.location();
+        // serde_yaml appends the location to its message; it's kept separately
+        let message = error.to_string();
+        let message = match message.split_once(" at line ") {
+            Some((message, _)) => message.to_string(),
+            None => message,
+        };
+        Error::Parse {
+            file: None,
+            line: location.as_ref().map(|l| l.line()),
+            column: location.as_ref().map(|l| l.column()),
+            message,
+        }
+    }
+
+    /// Sets the file a parse error occurred in.
+    pub fn in_file(self, path: PathBuf) -> Self {
+        match self {
+            Error::Parse {
+                line,
+                column,
+                message,
+                ..
+            } => Error::Parse {
+                file: Some(path),
+                line,
+                column,
+                message,
+            },
+            other => other,
+        }
+    }
+}
+
+fn format_parse_error(
+    file: &Option<PathBuf>,
+    line: Option<usize>,
+    column: Option<usize>,
+    message: &str,
+) -> String {
+    let file = match file {
+        Some(file) => file.display().to_string(),
+        None => "plan".to_string(),
+    };
+    match (line, column) {
+        (Some(line), Some(column)) => format!("{}:{}:{}: {}", file, line, column, message),
+        (Some(line), None) => format!("{}:{}: {}", file, line, message),
+        _ => format!("{}: {}", file, message),
+    }
+}
@@ -0,0 +1,161 @@
+use crate::internal::*;
+use chrono::{Duration, NaiveDate};
+use std::sync::OnceLock;
+
+/// Text formats for an agenda that can be pasted into notes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AgendaFormat {
+    Markdown,
+    Org,
+}
+
+/// A day of the agenda, along with its date if it's known.
+pub struct AgendaDay {
+    pub schedule: Schedule,
+    pub date: Option<NaiveDate>,
+}
+
+/// Writes the days as an agenda: a heading per day and per routine, with
+/// the routine's start and end times, and a checkbox list of its activities.
+/// An optional title is added as a heading above the days.
+///
+/// In org-mode, routines on a known date get an active timestamp so they
+/// show up in the org agenda.
+pub fn agenda(days: &[AgendaDay], title: Option<&str>, format: AgendaFormat) -> String {
+    let mut out = String::new();
+    let mut level = 1;
+    if let Some(title) = title {
+        push_heading(&mut out, format, level, title);
+        level += 1;
+    }
+
+    for day in days {
+        let heading = match day.date {
+            Some(date) => format!("{} {}", day.schedule.day.pretty(), date),
+            None => day.schedule.day.pretty().to_string(),
+        };
+        push_heading(&mut out, format, level, &heading);
+
+        for block in &day.schedule.blocks {
+            let times = format!(
+                "{}–{}",
+                TimeCursor::new(block.start).pretty(),
+                TimeCursor::new(block.end()).pretty()
+            );
+            push_heading(
+                &mut out,
+                format,
+                level + 1,
+                &format!("{} {}", times, block.name),
+            );
+
+            if let (AgendaFormat::Org, Some(date)) = (format, day.date) {
+                out.push_str(&org_timestamp(date, block.start, block.end()));
+                out.push('\n');
+            }
+            for activity in &block.activities {
+                let activity = match format {
+                    AgendaFormat::Markdown => activity.clone(),
+                    AgendaFormat::Org => markdown_to_org(activity),
+                };
+                out.push_str(&format!("- [ ] {}\n", activity));
+            }
+            if format == AgendaFormat::Markdown && !block.activities.is_empty() {
+                out.push('\n');
+            }
+        }
+    }
+    out
+}
+
+fn push_heading(out: &mut String, format: AgendaFormat, level: usize, text: &str) {
+    match format {
+        AgendaFormat::Markdown => out.push_str(&format!("{} {}\n\n", "#".repeat(level), text)),
+        AgendaFormat::Org => out.push_str(&format!("{} {}\n", "*".repeat(level), text)),
+    }
+}
+
+/// An org-mode active timestamp for the block, e.g. `<2026-03-02 Mon
+/// 09:00-12:00>`.  Times past midnight fall on the following day.
+fn org_timestamp(date: NaiveDate, start: u32, end: u32) -> String {
+    let at = |minutes: u32| {
+        let date = date + Duration::days((minutes / (24 * 60)) as i64);
+        (
+            date.format("%Y-%m-%d %a").to_string(),
+            TimeCursor::new(minutes).pretty(),
+        )
+    };
+    let (start_date, start_time) = at(start);
+    let (end_date, end_time) = at(end);
+    if start_date == end_date {
+        format!("<{} {}-{}>", start_date, start_time, end_time)
+    } else {
+        format!(
+            "<{} {}>--<{} {}>",
+            start_date, start_time, end_date, end_time
+        )
+    }
+}
+
+/// Converts the Markdown formatting activities can use to org-mode markup.
+fn markdown_to_org(text: &str) -> String {
+    static MARKUP: OnceLock<Regex> = OnceLock::new();
+    let markup = MARKUP.get_or_init(|| {
+        Regex::new(r"\*\*([^*]+)\*\*|\*([^*]+)\*|`([^`]+)`|~~([^~]+)~~|\[([^\]]*)\]\(([^)]*)\)")
+            .unwrap()
+    });
+    markup
+        .replace_all(text, |caps: &regex::Captures| {
+            if let Some(bold) = caps.get(1) {
+                format!("*{}*", bold.as_str())
+            } else if let Some(italic) = caps.get(2) {
+                format!("/{}/", italic.as_str())
+            } else if let Some(code) = caps.get(3) {
+                format!("~{}~", code.as_str())
+            } else if let Some(strike) = caps.get(4) {
+                format!("+{}+", strike.as_str())
+            } else {
+                format!("[[{}][{}]]", &caps[6], &caps[5])
+            }
+        })
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_markdown_to_org_markup() {
+        assert_eq!(markdown_to_org("plain text"), "plain text");
+        assert_eq!(
+            markdown_to_org("**bold** and *italic*"),
+            "*bold* and /italic/"
+        );
+        assert_eq!(markdown_to_org("run `cargo test`"), "run ~cargo test~");
+        assert_eq!(markdown_to_org("~~skip~~ this"), "+skip+ this");
+        assert_eq!(
+            markdown_to_org("review [open PRs](https://example.com/pulls)"),
+            "review [[https://example.com/pulls][open PRs]]"
+        );
+        assert_eq!(markdown_to_org("2 * 3 = 6"), "2 * 3 = 6");
+    }
+
+    #[test]
+    fn org_timestamps() {
+        let date = NaiveDate::from_ymd_opt(2024, 3, 4).unwrap();
+        assert_eq!(
+            org_timestamp(date, 9 * 60, 10 * 60 + 30),
+            "<2024-03-04 Mon 09:00-10:30>"
+        );
+        // A block running past midnight ends the next day
+        assert_eq!(
+            org_timestamp(date, 23 * 60, 25 * 60),
+            "<2024-03-04 Mon 23:00>--<2024-03-05 Tue 01:00>"
+        );
+        assert_eq!(
+            org_timestamp(date, 24 * 60 + 30, 25 * 60),
+            "<2024-03-05 Tue 00:30-01:00>"
+        );
+    }
+}
@@ -0,0 +1,60 @@
+use super::{week_svg, Palette};
+use crate::internal::*;
+
+/// A self-contained HTML page with the week drawn by [`week_svg`], set up
+/// to print on a single landscape page.
+pub fn week_html(plan: &Plan) -> Result<String> {
+    let palette = Palette::light()?;
+    let svg = week_svg(plan)?;
+    Ok(format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>Weekly routine</title>
+<style>
+@page {{ size: landscape; margin: 1cm; }}
+body {{
+    margin: 2rem;
+    background: #ffffff;
+    color: {text};
+    font-family: ui-monospace, Menlo, Consolas, monospace;
+}}
+h1 {{ margin: 0 0 1rem; color: {heading}; font-size: 1.4rem; font-weight: normal; }}
+svg {{ width: 100%; height: auto; }}
+@media print {{
+    body {{ margin: 0; }}
+    svg {{ max-height: 17cm; }}
+}}
+</style>
+</head>
+<body>
+<h1>Weekly routine</h1>
+{svg}</body>
+</html>
+"#,
+        text = palette.color("txt"),
+        heading = palette.color("heading"),
+        svg = svg,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn page_embeds_the_week_in_light_colors() {
+        let plan =
+            Plan::from_yaml("daily_routine:\n  - name: R&D\n    start: 9am\n    duration: 1h\n")
+                .unwrap();
+        let html = week_html(&plan).unwrap();
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains(&week_svg(&plan).unwrap()));
+        assert!(html.contains(">R&amp;D</text>"));
+        assert!(html.trim_end().ends_with("</html>"));
+
+        let palette = Palette::light().unwrap();
+        assert!(html.contains(&format!("color: {};", palette.color("txt"))));
+    }
+}
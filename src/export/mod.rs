@@ -0,0 +1,52 @@
+//! Renders the plan to files that are used outside the terminal, e.g. a
+//! printable week or an agenda for a note-taking app.
+//!
+mod agenda;
+mod html;
+mod svg;
+
+pub use agenda::*;
+pub use html::*;
+pub use svg::*;
+
+use crate::internal::*;
+use std::collections::BTreeMap;
+
+/// Colors for printed output, which always uses the light theme whatever
+/// the terminal theme is.  The pages are drawn on white, like the paper
+/// they're printed on, and the terminal theme's colors are chosen for the
+/// terminal's background, so e.g. the dark theme's light grey text would
+/// barely show.
+struct Palette {
+    colors: BTreeMap<String, String>,
+}
+
+impl Palette {
+    fn light() -> Result<Palette> {
+        Ok(Palette {
+            colors: crate::themes::built_in_colors("light")?,
+        })
+    }
+
+    /// The color as "#rrggbb".
+    fn color(&self, name: &str) -> String {
+        let value = self.colors.get(name).map(|v| v.as_str()).unwrap_or(name);
+        cprintln_color_hex(value)
+    }
+}
+
+/// The schedules for each day of the week, starting on Monday.
+fn week_schedules(plan: &Plan) -> Result<Vec<Schedule>> {
+    let schedules = DayOfWeek::week()
+        .iter()
+        .map(|day| Schedule::resolve(plan, day.clone()))
+        .collect::<Result<_, Error>>()?;
+    Ok(schedules)
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
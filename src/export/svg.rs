@@ -0,0 +1,203 @@
+use super::{escape_xml, week_schedules, Palette};
+use crate::internal::*;
+use crate::themes::routine_color;
+use std::fmt::Write;
+
+const WIDTH: f64 = 1120.0;
+const MARGIN: f64 = 16.0;
+/// Space on the left for the hour labels
+const AXIS_WIDTH: f64 = 56.0;
+/// Space at the top for the day names
+const GRID_TOP: f64 = 48.0;
+const GRID_HEIGHT: f64 = 980.0;
+const FONT: &str = "ui-monospace, Menlo, Consolas, monospace";
+const LINE_HEIGHT: f64 = 11.0;
+
+/// Draws the week as an SVG image: a column per day with each routine as a
+/// box whose height is proportional to its duration.  As much of the
+/// routine's time and activities as fits is written in the box, and all of
+/// it is in the box's tooltip.
+pub fn week_svg(plan: &Plan) -> Result<String> {
+    let palette = Palette::light()?;
+    let schedules = week_schedules(plan)?;
+
+    // The grid covers whole hours from the earliest start to the latest end
+    let blocks = schedules.iter().flat_map(|s| &s.blocks);
+    let start = blocks.clone().map(|b| b.start).min().unwrap_or(0) / 60 * 60;
+    let end = blocks
+        .map(|b| b.end())
+        .max()
+        .unwrap_or(24 * 60)
+        .div_ceil(60)
+        * 60;
+    let scale = GRID_HEIGHT / (end - start).max(60) as f64;
+    let column_width = (WIDTH - AXIS_WIDTH - MARGIN) / 7.0;
+    let height = GRID_TOP + GRID_HEIGHT + MARGIN;
+
+    let mut out = String::new();
+    writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{WIDTH}" height="{height}" viewBox="0 0 {WIDTH} {height}" font-family="{FONT}">"#
+    )?;
+    writeln!(
+        out,
+        r##"<rect width="100%" height="100%" fill="#ffffff"/>"##
+    )?;
+
+    for (i, schedule) in schedules.iter().enumerate() {
+        let x = AXIS_WIDTH + (i as f64 + 0.5) * column_width;
+        writeln!(
+            out,
+            r#"<text x="{x:.1}" y="{:.1}" text-anchor="middle" font-size="13" fill="{}">{}</text>"#,
+            GRID_TOP - 18.0,
+            palette.color("week_day"),
+            schedule.day.pretty()
+        )?;
+    }
+
+    for hour in (start / 60)..=(end / 60) {
+        let y = GRID_TOP + (hour * 60 - start) as f64 * scale;
+        writeln!(
+            out,
+            r#"<line x1="{AXIS_WIDTH}" y1="{y:.1}" x2="{:.1}" y2="{y:.1}" stroke="{}" stroke-width="0.5"/>"#,
+            WIDTH - MARGIN,
+            palette.color("rule")
+        )?;
+        writeln!(
+            out,
+            r#"<text x="{:.1}" y="{:.1}" text-anchor="end" font-size="10" fill="{}">{}</text>"#,
+            AXIS_WIDTH - 6.0,
+            y + 3.5,
+            palette.color("block_time"),
+            TimeCursor::new(hour * 60).pretty()
+        )?;
+    }
+
+    for (i, schedule) in schedules.iter().enumerate() {
+        let x = AXIS_WIDTH + i as f64 * column_width + 2.0;
+        for block in &schedule.blocks {
+            let y = GRID_TOP + (block.start - start) as f64 * scale;
+            let h = block.duration as f64 * scale;
+            render_block(&mut out, &palette, block, x, y, column_width - 4.0, h)?;
+        }
+    }
+
+    writeln!(out, "</svg>")?;
+    Ok(out)
+}
+
+fn render_block(
+    out: &mut String,
+    palette: &Palette,
+    block: &ScheduledBlock,
+    x: f64,
+    y: f64,
+    w: f64,
+    h: f64,
+) -> Result<()> {
+    let color = palette.color(routine_color(&block.name));
+    let times = format!(
+        "{}–{}",
+        TimeCursor::new(block.start).pretty(),
+        TimeCursor::new(block.end()).pretty()
+    );
+    let activities: Vec<String> = block
+        .activities
+        .iter()
+        .map(|a| cprintln_plain_text(a))
+        .collect();
+
+    let mut tooltip = format!("{} {}", block.name, times);
+    for activity in &activities {
+        tooltip.push_str(&format!("\n- {}", activity));
+    }
+
+    writeln!(out, "<g>")?;
+    writeln!(out, "<title>{}</title>", escape_xml(&tooltip))?;
+    writeln!(
+        out,
+        r#"<rect x="{x:.1}" y="{y:.1}" width="{w:.1}" height="{h:.1}" fill="{color}" fill-opacity="0.15"/>"#
+    )?;
+    writeln!(
+        out,
+        r#"<rect x="{x:.1}" y="{y:.1}" width="3" height="{h:.1}" fill="{color}"/>"#
+    )?;
+
+    // Caption lines, as many as fit in the box
+    let mut lines = vec![(block.name.clone(), color.as_str(), "bold", 11.0)];
+    let time_color = palette.color("block_time");
+    let activity_color = palette.color("txt");
+    lines.push((times, time_color.as_str(), "normal", 9.0));
+    for activity in activities {
+        lines.push((activity, activity_color.as_str(), "normal", 9.0));
+    }
+
+    let mut baseline = y + LINE_HEIGHT;
+    for (text, fill, weight, size) in lines {
+        if baseline > y + h - 1.0 {
+            break;
+        }
+        let max_chars = ((w - 8.0) / (size * 0.6)).max(1.0) as usize;
+        writeln!(
+            out,
+            r#"<text x="{:.1}" y="{baseline:.1}" font-size="{size}" font-weight="{weight}" fill="{fill}">{}</text>"#,
+            x + 6.0,
+            escape_xml(&truncate(&text, max_chars))
+        )?;
+        baseline += LINE_HEIGHT;
+    }
+
+    writeln!(out, "</g>")?;
+    Ok(())
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(max_chars.saturating_sub(1)).collect();
+    truncated.push('…');
+    truncated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAN: &str = "\
+daily_routine:
+  - name: R&D <lab>
+    start: 9am
+    duration: 2h
+    activities:
+      default: [check 1 < 2 & 3 > 2]
+";
+
+    #[test]
+    fn week_is_well_formed_svg() {
+        let svg = week_svg(&Plan::from_yaml(PLAN).unwrap()).unwrap();
+        let doc = roxmltree::Document::parse(&svg).unwrap();
+        assert_eq!(doc.root_element().tag_name().name(), "svg");
+
+        let texts: Vec<&str> = doc.descendants().filter_map(|n| n.text()).collect();
+        for day in DayOfWeek::week() {
+            assert!(texts.contains(&day.pretty()));
+        }
+        // One box for each day, with its tooltip
+        let titles: Vec<&str> = doc
+            .descendants()
+            .filter(|n| n.has_tag_name("title"))
+            .filter_map(|n| n.text())
+            .collect();
+        assert_eq!(titles.len(), 7);
+        assert_eq!(titles[0], "R&D <lab> 09:00–11:00\n- check 1 < 2 & 3 > 2");
+    }
+
+    #[test]
+    fn names_and_activities_are_escaped() {
+        let svg = week_svg(&Plan::from_yaml(PLAN).unwrap()).unwrap();
+        assert!(svg.contains(">R&amp;D &lt;lab&gt;</text>"));
+        assert!(svg.contains(">check 1 &lt; 2 &amp; 3 &gt; 2</text>"));
+        assert!(!svg.contains("<lab>"));
+    }
+}
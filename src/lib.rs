@@ -0,0 +1,45 @@
+//! The guidebook-plan library: the plan model along with helpers to load,
+//! validate, resolve, and save plans.  The `guidebook-plan` command-line
+//! tool is a front-end on top of it.
+//!
+//! - [`App`] locates the guidebook data directory and loads and saves the
+//!   plan file, either as a [`Plan`] or as a [`PlanDocument`], which keeps
+//!   the comments and formatting of the file when it's modified.
+//! - [`Plan::validate`] checks every routine's times and durations.
+//! - [`Schedule::resolve`] turns a plan into concrete blocks of time for a
+//!   given [`DayOfWeek`], following `after` anchors and `fill` durations.
+//! - [`repo::Repo`] runs git operations on the data repository, in-process
+//!   by default.
+//! - [`templates::Template`] loads the built-in plan templates, or one from
+//!   a path or git repository.
+//!
+//! The library never prints or exits the process: all failures are
+//! returned to the caller as an [`Error`], whose variants are the kinds of
+//! failure that can be told apart.
+//!
+#![warn(missing_docs)]
+
+mod app;
+mod error;
+pub mod model;
+pub mod repo;
+pub mod templates;
+pub mod util;
+
+pub use app::{App, PLAN_RELATIVE_PATH};
+pub use error::{Error, Result};
+pub use model::*;
+
+/// Prelude used by the modules of this crate.
+pub(crate) mod internal {
+    pub use super::app::PLAN_RELATIVE_PATH;
+    pub(crate) use super::error::invalid;
+    pub use super::error::{Error, Result};
+    pub use super::model::*;
+    pub use super::repo::Repo;
+    pub use super::util::*;
+
+    pub use schemars::JsonSchema;
+    pub use serde::{Deserialize, Serialize};
+    pub use std::collections::HashMap;
+}
@@ -1,15 +1,23 @@
-mod app;
+//! The guidebook-plan command-line tool, a thin front-end over the
+//! guidebook_plan library.
+//!
 mod commands;
-mod model;
-pub mod util;
+mod export;
+mod server;
+mod themes;
+mod ui;
 
+/// Prelude for the modules of the command-line tool.  It only uses the
+/// library's public API, like any other program built on it.
 pub mod internal {
-    pub use super::app::App;
-    pub use super::model::*;
-    pub use super::util::*;
+    pub use super::ui::*;
+    pub use guidebook_plan::repo::Repo;
+    pub use guidebook_plan::util::*;
+    pub use guidebook_plan::*;
 
     pub use anyhow::{anyhow, Result};
     pub use regex::Regex;
+    pub use schemars::JsonSchema;
     pub use serde::{Deserialize, Serialize};
     pub use std::collections::HashMap;
 }
@@ -24,61 +32,203 @@ use commands::*;
 struct Cli {
     #[command(subcommand)]
     command: Option<Commands>,
+
+    /// when to use colors in the output
+    #[arg(long, global = true, value_enum, default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
 }
 
 #[derive(clap::Subcommand)]
 enum Commands {
     /// initialize the data repository
-    Init,
+    Init {
+        /// template for the initial plan: a built-in name, path, or git URL
+        #[arg(long)]
+        template: Option<String>,
+    },
     /// clone a remote data repository
     Clone,
+    /// show routine changes between two revisions of the plan
+    Diff {
+        /// the older revision (defaults to HEAD)
+        rev1: Option<String>,
+        /// the newer revision (defaults to the working tree)
+        rev2: Option<String>,
+        /// width to lay out the output for (defaults to the terminal width)
+        #[arg(long)]
+        width: Option<usize>,
+    },
+    /// export the plan to a file, e.g. to print it or paste it into notes
+    Export {
+        /// format to export to
+        #[arg(value_enum)]
+        format: ExportFormat,
+        /// day to export for md and org: a weekday, today, tomorrow, yesterday, or YYYY-MM-DD
+        #[arg(long)]
+        day: Option<DaySelector>,
+        /// export the whole week for md and org
+        #[arg(long, conflicts_with = "day")]
+        week: bool,
+        /// file to write to (defaults to stdout)
+        #[arg(long, short)]
+        output: Option<std::path::PathBuf>,
+    },
+    /// rewrite the plan file in a canonical form
+    Fmt {
+        /// check that the plan is formatted without changing it
+        #[arg(long)]
+        check: bool,
+    },
+    /// build the plan from a file, e.g. a spreadsheet saved as CSV
+    Import {
+        /// format of the file
+        #[arg(value_enum)]
+        format: ImportFormat,
+        file: std::path::PathBuf,
+        /// add the routines to the current plan instead of replacing it
+        #[arg(long)]
+        merge: bool,
+        /// write the plan without asking for confirmation
+        #[arg(long, short)]
+        yes: bool,
+        /// width to lay out the preview for (defaults to the terminal width)
+        #[arg(long)]
+        width: Option<usize>,
+    },
     /// open the plan file in the editor
     Open,
     /// push changes to the remote repository
     Push,
+    /// add, remove, or change routines in the plan
+    Routine {
+        #[command(subcommand)]
+        command: RoutineCommand,
+    },
+    /// add or remove activities of a routine
+    Activity {
+        #[command(subcommand)]
+        command: ActivityCommand,
+    },
+    /// list and preview plan templates
+    Template {
+        #[command(subcommand)]
+        command: TemplateCommand,
+    },
+    /// push the rest of today's schedule later, e.g. when running late
+    Shift {
+        /// how far to shift, e.g. +20m (or -10m to move earlier)
+        #[arg(allow_hyphen_values = true)]
+        amount: Option<String>,
+        /// first routine to move (defaults to the one after the current routine)
+        #[arg(long)]
+        from: Option<String>,
+        /// shorten flexible routines so those with fixed times stay put
+        #[arg(long)]
+        compress: bool,
+        /// remove all of today's shifts
+        #[arg(long, conflicts_with = "amount")]
+        clear: bool,
+    },
+    /// print the JSON Schema for plan.yaml, for validation in editors
+    Schema {
+        /// file to write to (defaults to stdout)
+        #[arg(long, short)]
+        output: Option<std::path::PathBuf>,
+    },
+    /// serve the plan as JSON on localhost for dashboards and widgets
+    Serve {
+        /// port to listen on
+        #[arg(long, default_value_t = 7777)]
+        port: u16,
+        /// let pages from this origin read the plan, e.g. http://localhost:3000
+        #[arg(long)]
+        allow_origin: Option<String>,
+    },
+    /// show the plan for every day of the week side by side
+    Week {
+        /// width to lay out the output for (defaults to the terminal width)
+        #[arg(long)]
+        width: Option<usize>,
+    },
     /// show the current plan
-    Show,
+    Show {
+        /// day to show instead of today: a weekday, tomorrow, yesterday, or YYYY-MM-DD
+        day: Option<DaySelector>,
+        /// show the plan as it was committed on this date (YYYY-MM-DD)
+        #[arg(long)]
+        as_of: Option<chrono::NaiveDate>,
+        /// width to lay out the output for (defaults to the terminal width)
+        #[arg(long)]
+        width: Option<usize>,
+    },
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let app = App::new();
     let cli = Cli::parse();
-    let command = cli.command.unwrap_or(Commands::Show);
+    cprintln_set_color_choice(cli.color);
+    if let Err(e) = themes::load_theme() {
+        cprintln!("warn", "{}", e);
+    }
+    let command = cli.command.unwrap_or(Commands::Show {
+        day: None,
+        as_of: None,
+        width: None,
+    });
 
     // Check the data directory exists before proceeding with any commands
     // that require it.
-    match command {
+    let result = match command {
         // These commands do not require the guidebook data dir
-        Commands::Init | Commands::Clone => {}
-        _ => {
-            if !app.guidebook_root_exists() {
-                cprintln!(
-                    "",
-                    r#"
-[ERROR: guidebook data directory not found](error)
-
-Run the following command to initialize your guidebook repository:
-[guidebook-plan init](command)
-
-This will create a remote and local data directory as needed.
-"#
-                );
-                std::process::exit(1);
-            };
-        }
+        Commands::Init { .. }
+        | Commands::Clone
+        | Commands::Schema { .. }
+        | Commands::Template { .. } => run(command).await,
+        _ => match app.guidebook_root(false) {
+            Ok(_) => run(command).await,
+            Err(e) => Err(e.into()),
+        },
+    };
+    if let Err(e) = result {
+        std::process::exit(report_error(&e));
     }
+    Ok(())
+}
 
-    let result = match command {
-        Commands::Init => command_init().await,
+async fn run(command: Commands) -> Result<()> {
+    match command {
+        Commands::Init { template } => command_init(template).await,
         Commands::Clone => command_clone(),
+        Commands::Diff { rev1, rev2, width } => command_diff(rev1, rev2, width),
+        Commands::Export {
+            format,
+            day,
+            week,
+            output,
+        } => command_export(format, day, week, output),
+        Commands::Fmt { check } => command_fmt(check),
+        Commands::Import {
+            format,
+            file,
+            merge,
+            yes,
+            width,
+        } => command_import(format, file, merge, yes, width),
         Commands::Open => command_open(),
         Commands::Push => command_push(),
-        Commands::Show => command_show(),
-    };
-    if let Err(e) = result {
-        eprintln!("ERROR: {}", e);
-        std::process::exit(1);
+        Commands::Routine { command } => command_routine(command),
+        Commands::Activity { command } => command_activity(command),
+        Commands::Template { command } => command_template(command),
+        Commands::Shift {
+            amount,
+            from,
+            compress,
+            clear,
+        } => command_shift(amount, from, compress, clear),
+        Commands::Schema { output } => command_schema(output),
+        Commands::Serve { port, allow_origin } => command_serve(port, allow_origin).await,
+        Commands::Week { width } => command_week(width),
+        Commands::Show { day, as_of, width } => command_show(day, as_of, width),
     }
-    Ok(())
 }
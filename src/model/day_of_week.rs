@@ -1,6 +1,16 @@
 use crate::internal::*;
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+/// A key of a routine's activities: a day of the week (`mon` to `sun`), or
+/// a group of days.  `everyday` activities are added to every day,
+/// `weekdays` to Monday to Friday, and `weekends` to Saturday and Sunday;
+/// `default` activities are used on days that have no list of their own.
+///
+/// The variants are deliberately undocumented: with doc comments, the
+/// JSON Schema would no longer list the keys allowed under `activities`.
+#[allow(missing_docs)]
+#[derive(
+    Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema,
+)]
 #[serde(rename_all = "lowercase")]
 pub enum DayOfWeek {
     Mon,
@@ -15,3 +25,189 @@ pub enum DayOfWeek {
     Weekdays,
     Weekends,
 }
+
+impl DayOfWeek {
+    /// All keys in the order they are written to plan.yaml: the general
+    /// keys first, followed by the specific days of the week.
+    pub fn all() -> &'static [DayOfWeek] {
+        &[
+            DayOfWeek::Default,
+            DayOfWeek::Everyday,
+            DayOfWeek::Weekdays,
+            DayOfWeek::Weekends,
+            DayOfWeek::Mon,
+            DayOfWeek::Tue,
+            DayOfWeek::Wed,
+            DayOfWeek::Thu,
+            DayOfWeek::Fri,
+            DayOfWeek::Sat,
+            DayOfWeek::Sun,
+        ]
+    }
+
+    /// The seven days of the week, starting on Monday.
+    pub fn week() -> &'static [DayOfWeek] {
+        &DayOfWeek::all()[4..]
+    }
+
+    /// The day for a chrono weekday.
+    pub fn from_weekday(weekday: chrono::Weekday) -> Self {
+        match weekday {
+            chrono::Weekday::Mon => DayOfWeek::Mon,
+            chrono::Weekday::Tue => DayOfWeek::Tue,
+            chrono::Weekday::Wed => DayOfWeek::Wed,
+            chrono::Weekday::Thu => DayOfWeek::Thu,
+            chrono::Weekday::Fri => DayOfWeek::Fri,
+            chrono::Weekday::Sat => DayOfWeek::Sat,
+            chrono::Weekday::Sun => DayOfWeek::Sun,
+        }
+    }
+
+    /// The full name of a day of the week, e.g. "Monday".
+    pub fn pretty(&self) -> &'static str {
+        match self {
+            DayOfWeek::Mon => "Monday",
+            DayOfWeek::Tue => "Tuesday",
+            DayOfWeek::Wed => "Wednesday",
+            DayOfWeek::Thu => "Thursday",
+            DayOfWeek::Fri => "Friday",
+            DayOfWeek::Sat => "Saturday",
+            DayOfWeek::Sun => "Sunday",
+            _ => "Unknown",
+        }
+    }
+
+    /// The key used for this day in the plan.yaml file.
+    pub fn key(&self) -> &'static str {
+        match self {
+            DayOfWeek::Mon => "mon",
+            DayOfWeek::Tue => "tue",
+            DayOfWeek::Wed => "wed",
+            DayOfWeek::Thu => "thu",
+            DayOfWeek::Fri => "fri",
+            DayOfWeek::Sat => "sat",
+            DayOfWeek::Sun => "sun",
+            DayOfWeek::Everyday => "everyday",
+            DayOfWeek::Default => "default",
+            DayOfWeek::Weekdays => "weekdays",
+            DayOfWeek::Weekends => "weekends",
+        }
+    }
+}
+
+impl std::str::FromStr for DayOfWeek {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let key = s.trim().to_lowercase();
+        DayOfWeek::all()
+            .iter()
+            .find(|day| day.key() == key || day.pretty().to_lowercase() == key)
+            .cloned()
+            .ok_or_else(|| invalid!("Unknown day '{}'", s))
+    }
+}
+
+/// A day to show the plan for, as given on the command line.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DaySelector {
+    /// A specific calendar date, including "today", "tomorrow", and "yesterday"
+    Date(chrono::NaiveDate),
+    /// A day of the week without a specific date
+    Weekday(DayOfWeek),
+}
+
+impl DaySelector {
+    /// The day of the week to use from the plan, along with the date if known.
+    pub fn resolve(&self) -> (DayOfWeek, Option<chrono::NaiveDate>) {
+        use chrono::Datelike;
+        match self {
+            DaySelector::Date(date) => (DayOfWeek::from_weekday(date.weekday()), Some(*date)),
+            DaySelector::Weekday(day) => (day.clone(), None),
+        }
+    }
+}
+
+impl std::str::FromStr for DaySelector {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let today = chrono::Local::now().date_naive();
+        let key = s.trim().to_lowercase();
+        let date = match key.as_str() {
+            "today" => Some(today),
+            "tomorrow" => today.succ_opt(),
+            "yesterday" => today.pred_opt(),
+            _ => chrono::NaiveDate::parse_from_str(&key, "%Y-%m-%d").ok(),
+        };
+        if let Some(date) = date {
+            return Ok(DaySelector::Date(date));
+        }
+
+        match key.parse::<DayOfWeek>() {
+            Ok(day) if DayOfWeek::week().contains(&day) => Ok(DaySelector::Weekday(day)),
+            _ => Err(invalid!(
+                "Unknown day '{}'. Use a day of the week, today, tomorrow, yesterday, or a YYYY-MM-DD date",
+                s
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn parse(s: &str) -> DaySelector {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_days_of_the_week() {
+        assert_eq!(parse("mon"), DaySelector::Weekday(DayOfWeek::Mon));
+        assert_eq!(parse("Friday"), DaySelector::Weekday(DayOfWeek::Fri));
+        assert_eq!(parse(" SUN "), DaySelector::Weekday(DayOfWeek::Sun));
+    }
+
+    #[test]
+    fn parses_relative_days() {
+        let today = chrono::Local::now().date_naive();
+        assert_eq!(parse("today"), DaySelector::Date(today));
+        assert_eq!(
+            parse("Tomorrow"),
+            DaySelector::Date(today.succ_opt().unwrap())
+        );
+        assert_eq!(
+            parse("yesterday"),
+            DaySelector::Date(today.pred_opt().unwrap())
+        );
+    }
+
+    #[test]
+    fn parses_dates() {
+        let date = NaiveDate::from_ymd_opt(2024, 2, 29).unwrap();
+        assert_eq!(parse("2024-02-29"), DaySelector::Date(date));
+        assert_eq!(parse("2024-02-29").resolve(), (DayOfWeek::Thu, Some(date)));
+    }
+
+    #[test]
+    fn rejects_other_days() {
+        for s in [
+            "",
+            "someday",
+            "weekdays",
+            "default",
+            "2024-02-30",
+            "2024/02/29",
+        ] {
+            let error = s.parse::<DaySelector>().unwrap_err();
+            assert!(
+                matches!(&error, Error::Invalid(message) if message.starts_with("Unknown day")),
+                "{:?} gave {}",
+                s,
+                error
+            );
+        }
+    }
+}
@@ -1,7 +1,23 @@
+//! The plan format and what can be worked out from it: routines, the
+//! schedule of a day, shifts, and differences between plans.
+//!
 mod day_of_week;
 mod plan;
+mod plan_csv;
+mod plan_diff;
+mod plan_document;
+mod plan_schema;
 mod routine;
+mod schedule;
+mod shift;
+mod time_grammar;
 
 pub use day_of_week::*;
 pub use plan::*;
+pub use plan_diff::*;
+pub use plan_document::*;
+pub use plan_schema::*;
 pub use routine::*;
+pub use schedule::*;
+pub use shift::*;
+pub use time_grammar::*;
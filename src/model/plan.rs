@@ -1,12 +1,15 @@
 use crate::internal::*;
-use chrono::{Datelike, Local, Timelike, Weekday};
+use chrono::{Datelike, Local, Timelike};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A daily routine, as stored in plan.yaml.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct Plan {
+    /// The routines of the day, in the order they happen
     pub daily_routine: Vec<Routine>,
 }
 
 impl Plan {
+    /// Normalizes a plan as read from plan.yaml.
     pub fn new(mut data: Plan) -> Self {
         // Normalize routines
         for routine in &mut data.daily_routine {
@@ -15,50 +18,134 @@ impl Plan {
         data
     }
 
+    /// Parses and normalizes a plan from the text of a plan.yaml file.
+    pub fn from_yaml(text: &str) -> Result<Self> {
+        let data: Plan = serde_yaml::from_str(text).map_err(|e| Error::from_yaml(&e))?;
+        Ok(Plan::new(data))
+    }
+
+    /// Serializes the plan to YAML.  This doesn't keep the comments or
+    /// formatting of an existing file; use PlanDocument for that.
+    pub fn to_yaml(&self) -> Result<String> {
+        Ok(serde_yaml::to_string(self)?)
+    }
+
+    /// Parses a plan like [`Plan::from_yaml`] and checks it like
+    /// [`Plan::validate`], giving the line of the routine with a problem.
+    pub fn from_yaml_validated(text: &str) -> Result<Self> {
+        let plan = Plan::from_yaml(text)?;
+        plan.check(&PlanDocument::routine_lines(text, &plan))?;
+        Ok(plan)
+    }
+
+    /// Checks that the times and durations of every routine are valid.
+    /// Problems are reported as [`Error::Parse`].
+    pub fn validate(&self) -> Result<()> {
+        self.check(&[])
+    }
+
+    /// Validates each routine, reporting a problem at the routine's line
+    /// when it's known.
+    fn check(&self, lines: &[usize]) -> Result<()> {
+        for (index, routine) in self.daily_routine.iter().enumerate() {
+            routine.validate().map_err(|e| {
+                let message = match e {
+                    Error::Time(e) => format!("Routine '{}': {}", routine.name, e),
+                    e => e.to_string(),
+                };
+                Error::Parse {
+                    file: None,
+                    line: lines.get(index).copied(),
+                    column: None,
+                    message,
+                }
+            })?;
+        }
+        Ok(())
+    }
+
+    /// The routines of the day, in order.
     pub fn routines(&self) -> &[Routine] {
         &self.daily_routine
     }
 
-    pub fn current_day_of_week(&self) -> DayOfWeek {
-        let today = Local::now().weekday();
-        match today {
-            Weekday::Mon => DayOfWeek::Mon,
-            Weekday::Tue => DayOfWeek::Tue,
-            Weekday::Wed => DayOfWeek::Wed,
-            Weekday::Thu => DayOfWeek::Thu,
-            Weekday::Fri => DayOfWeek::Fri,
-            Weekday::Sat => DayOfWeek::Sat,
-            Weekday::Sun => DayOfWeek::Sun,
+    /// Finds the index of a routine given either its name or its 1-based
+    /// position in the daily routine.  A name wins over a position, so a
+    /// routine named e.g. "2" can still be selected by name.
+    pub fn find_routine(&self, selector: &str) -> Result<usize> {
+        let matches: Vec<usize> = self
+            .daily_routine
+            .iter()
+            .enumerate()
+            .filter(|(_, r)| r.name.eq_ignore_ascii_case(selector))
+            .map(|(i, _)| i)
+            .collect();
+
+        match matches.as_slice() {
+            [index] => Ok(*index),
+            [] => match selector.parse::<usize>() {
+                Ok(position) if position == 0 || position > self.daily_routine.len() => {
+                    Err(invalid!(
+                        "Routine position {} is out of range (1-{})",
+                        position,
+                        self.daily_routine.len()
+                    ))
+                }
+                Ok(position) => Ok(position - 1),
+                Err(_) => Err(invalid!("No routine named '{}'", selector)),
+            },
+            _ => Err(invalid!(
+                "More than one routine is named '{}'; use its position instead (one of {})",
+                selector,
+                matches
+                    .iter()
+                    .map(|i| (i + 1).to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
         }
     }
 
+    /// Indices of the routines anchored after the named routine (ignoring
+    /// case) with `after`.
+    pub fn anchored_after(&self, name: &str) -> Vec<usize> {
+        self.daily_routine
+            .iter()
+            .enumerate()
+            .filter(|(_, r)| {
+                r.after_anchor()
+                    .ok()
+                    .flatten()
+                    .is_some_and(|(anchor, _)| anchor.eq_ignore_ascii_case(name))
+            })
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Today's day of the week, in local time.
+    pub fn current_day_of_week(&self) -> DayOfWeek {
+        DayOfWeek::from_weekday(Local::now().weekday())
+    }
+
+    /// Today's day of the week, e.g. "Monday".
     pub fn current_day_of_week_pretty(&self) -> String {
-        let day = self.current_day_of_week();
-        match day {
-            DayOfWeek::Mon => "Monday",
-            DayOfWeek::Tue => "Tuesday",
-            DayOfWeek::Wed => "Wednesday",
-            DayOfWeek::Thu => "Thursday",
-            DayOfWeek::Fri => "Friday",
-            DayOfWeek::Sat => "Saturday",
-            DayOfWeek::Sun => "Sunday",
-            _ => "Unknown",
-        }
-        .to_string()
+        self.current_day_of_week().pretty().to_string()
     }
 
+    /// Minutes since midnight, in local time.
     pub fn current_time_mins(&self) -> u32 {
         let now = Local::now();
-        (now.hour() * 60 + now.minute()) as u32
+        now.hour() * 60 + now.minute()
     }
 
+    /// The local time on a 12-hour clock, e.g. "9:05 am".
     pub fn current_time_pretty(&self) -> String {
         let now = Local::now();
         let mut hours = now.hour();
         let minutes = now.minute();
         let ampm = if hours >= 12 { "pm" } else { "am" };
 
-        hours = hours % 12;
+        hours %= 12;
         if hours == 0 {
             hours = 12;
         }
@@ -66,27 +153,78 @@ impl Plan {
         format!("{}:{:02} {}", hours, minutes, ampm)
     }
 
+    /// Index of the routine in progress now, or -1 if there is none.
     pub fn current_activity_index(&self) -> Result<i32> {
-        let current = self.current_time_mins();
-        if self.daily_routine.is_empty() {
-            return Ok(-1);
+        self.activity_index_at(self.current_time_mins())
+    }
+
+    /// Index of the routine in progress at the given time-of-day (in
+    /// minutes) today, or -1 if there is none.
+    pub fn activity_index_at(&self, current: u32) -> Result<i32> {
+        let schedule = Schedule::resolve(self, self.current_day_of_week())?;
+        Ok(match schedule.index_at(current) {
+            Some(i) => schedule.blocks[i].index as i32,
+            None => -1,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_error(text: &str) -> (Option<usize>, String) {
+        let error = Plan::from_yaml_validated(text).unwrap_err();
+        match error {
+            Error::Parse { line, message, .. } => (line, message),
+            _ => panic!("not a parse error: {}", error),
         }
+    }
 
-        let first_start = self.daily_routine[0].start_minutes()?.unwrap_or(0);
-        let mut cursor = TimeCursor::new(first_start);
+    #[test]
+    fn invalid_routines_are_parse_errors_at_their_line() {
+        let text = "\
+daily_routine:
+  - name: Wake
+    start: 7am
+    duration: 30m
 
-        for (index, routine) in self.daily_routine.iter().enumerate() {
-            if let Some(activity_start) = routine.start_minutes()? {
-                cursor.set(activity_start);
-            }
+  # Mornings only
+  - name: Work
+    duration: abc
+";
+        let (line, message) = parse_error(text);
+        assert_eq!(line, Some(7));
+        assert!(message.starts_with("Routine 'Work': invalid duration 'abc'"));
 
-            if cursor.cursor > current {
-                return Ok(index as i32 - 1);
-            }
+        let text = "daily_routine:\n  - name: Work\n    start: 9am\n";
+        let (line, message) = parse_error(text);
+        assert_eq!(line, Some(2));
+        assert_eq!(
+            message,
+            "Routine 'Work' needs either a duration or an end time"
+        );
+    }
 
-            cursor.add(routine.duration()? as u32);
-        }
+    #[test]
+    fn find_routine_by_name_or_position() {
+        let plan = Plan::from_yaml(
+            "daily_routine:\n  - name: Wake\n  - name: '1'\n  - name: Work\n  - name: Work\n",
+        )
+        .unwrap();
+        assert_eq!(plan.find_routine("wake").unwrap(), 0);
+        assert_eq!(plan.find_routine("1").unwrap(), 1);
+        assert_eq!(plan.find_routine("3").unwrap(), 2);
+        assert!(plan.find_routine("5").is_err());
+        assert!(plan.find_routine("0").is_err());
+        assert!(plan.find_routine("Work").is_err());
+        assert!(plan.find_routine("Sleep").is_err());
+    }
 
-        Ok(-1)
+    #[test]
+    fn validate_without_source_has_no_line() {
+        let plan = Plan::from_yaml("daily_routine:\n  - name: Work\n    start: 25:00\n").unwrap();
+        let error = plan.validate().unwrap_err();
+        assert!(matches!(error, Error::Parse { line: None, .. }));
     }
 }
@@ -0,0 +1,218 @@
+//! Builds a plan from a CSV file, e.g. a routine drafted in a spreadsheet.
+//!
+//! Each row describes a routine and optionally one of its activities:
+//!
+//! ```text
+//! name,start,duration,day,activity
+//! Work,9am,3h,,
+//! Work,,,mon,Standup
+//! Work,,,fri,Demo
+//! Lunch,,1h,,
+//! ```
+//!
+//! Rows with the same name belong to the same routine, in the order the
+//! name first appears.  Only the `name` column is required; the header is
+//! case-insensitive and the columns can be in any order.  An activity
+//! without a day applies to every day (`default`).
+//!
+use crate::internal::*;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct CsvRow {
+    name: Option<String>,
+    start: Option<String>,
+    duration: Option<String>,
+    day: Option<String>,
+    activity: Option<String>,
+}
+
+impl Plan {
+    /// Parses a plan from CSV text.  The plan isn't validated, so the caller
+    /// can report timing problems after merging it into another plan.
+    pub fn from_csv(text: &str) -> Result<Plan> {
+        let mut reader = csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .flexible(true)
+            .from_reader(text.as_bytes());
+
+        let headers: csv::StringRecord = reader
+            .headers()
+            .map_err(csv_error)?
+            .iter()
+            .map(|h| h.to_lowercase())
+            .collect();
+        if !headers.iter().any(|h| h == "name") {
+            return Err(parse_error(1, "the header has no 'name' column"));
+        }
+        reader.set_headers(headers.clone());
+
+        let mut routines: Vec<Routine> = Vec::new();
+        for record in reader.records() {
+            let record = record.map_err(csv_error)?;
+            let line = record.position().map_or(0, |p| p.line() as usize);
+            let row: CsvRow = record.deserialize(Some(&headers)).map_err(csv_error)?;
+
+            let Some(name) = non_empty(row.name) else {
+                if record.iter().all(|field| field.is_empty()) {
+                    continue;
+                }
+                return Err(parse_error(line, "missing routine name"));
+            };
+
+            let index = match routines
+                .iter()
+                .position(|r| r.name.eq_ignore_ascii_case(&name))
+            {
+                Some(index) => index,
+                None => {
+                    routines.push(Routine {
+                        name: name.clone(),
+                        start: None,
+                        after: None,
+                        end: None,
+                        duration: None,
+                        activities: HashMap::new(),
+                    });
+                    routines.len() - 1
+                }
+            };
+            let routine = &mut routines[index];
+
+            for (field, value, column) in [
+                (&mut routine.start, non_empty(row.start), "start"),
+                (&mut routine.duration, non_empty(row.duration), "duration"),
+            ] {
+                match (field.as_ref(), value) {
+                    (Some(existing), Some(value)) if *existing != value => {
+                        return Err(parse_error(
+                            line,
+                            &format!(
+                                "'{}' already has {} '{}' but this row gives '{}'",
+                                name, column, existing, value
+                            ),
+                        ));
+                    }
+                    (None, Some(value)) => *field = Some(value),
+                    _ => {}
+                }
+            }
+
+            let day = match non_empty(row.day) {
+                Some(day) => day
+                    .parse::<DayOfWeek>()
+                    .map_err(|e| parse_error(line, &e.to_string()))?,
+                None => DayOfWeek::Default,
+            };
+            if let Some(activity) = non_empty(row.activity) {
+                routine.activities.entry(day).or_default().push(activity);
+            }
+        }
+
+        Ok(Plan::new(Plan {
+            daily_routine: routines,
+        }))
+    }
+}
+
+fn non_empty(value: Option<String>) -> Option<String> {
+    value.filter(|v| !v.is_empty())
+}
+
+fn parse_error(line: usize, message: &str) -> Error {
+    Error::Parse {
+        file: None,
+        line: Some(line),
+        column: None,
+        message: message.to_string(),
+    }
+}
+
+fn csv_error(error: csv::Error) -> Error {
+    let line = error.position().map(|p| p.line() as usize);
+    Error::Parse {
+        file: None,
+        line,
+        column: None,
+        message: error.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_error_line(text: &str) -> (Option<usize>, String) {
+        let error = Plan::from_csv(text).unwrap_err();
+        match error {
+            Error::Parse { line, message, .. } => (line, message),
+            _ => panic!("not a parse error: {}", error),
+        }
+    }
+
+    #[test]
+    fn rows_with_the_same_name_build_one_routine() {
+        let plan = Plan::from_csv(
+            "name,start,duration,day,activity
+Work,9AM,3h,,
+work,,,mon,Standup
+Work,,,Fri,Demo
+Work,,,,Inbox
+Lunch,,1h,,
+",
+        )
+        .unwrap();
+
+        assert_eq!(plan.daily_routine.len(), 2);
+        let work = &plan.daily_routine[0];
+        assert_eq!(work.name, "Work");
+        assert_eq!(work.start.as_deref(), Some("9am"));
+        assert_eq!(work.duration.as_deref(), Some("3h"));
+        assert_eq!(work.activities[&DayOfWeek::Mon], vec!["Standup"]);
+        assert_eq!(work.activities[&DayOfWeek::Fri], vec!["Demo"]);
+        assert_eq!(work.activities[&DayOfWeek::Default], vec!["Inbox"]);
+        assert_eq!(plan.daily_routine[1].name, "Lunch");
+    }
+
+    #[test]
+    fn header_is_case_insensitive_and_in_any_order() {
+        let plan = Plan::from_csv(
+            "Duration, NAME ,Start
+30m,Wake,7am
+",
+        )
+        .unwrap();
+        let wake = &plan.daily_routine[0];
+        assert_eq!(wake.name, "Wake");
+        assert_eq!(wake.start.as_deref(), Some("7am"));
+        assert_eq!(wake.duration.as_deref(), Some("30m"));
+    }
+
+    #[test]
+    fn blank_rows_are_skipped() {
+        let plan = Plan::from_csv("name,duration\nWake,30m\n,\nWork,3h\n").unwrap();
+        assert_eq!(plan.daily_routine.len(), 2);
+    }
+
+    #[test]
+    fn errors_have_line_numbers() {
+        assert_eq!(
+            parse_error_line("start,duration\n9am,1h\n"),
+            (Some(1), "the header has no 'name' column".to_string())
+        );
+        assert_eq!(
+            parse_error_line("name,start\nWork,9am\n,10am\n"),
+            (Some(3), "missing routine name".to_string())
+        );
+        assert_eq!(
+            parse_error_line("name,start\nWork,9am\nWork,10am\n"),
+            (
+                Some(3),
+                "'Work' already has start '9am' but this row gives '10am'".to_string()
+            )
+        );
+        let (line, message) = parse_error_line("name,day,activity\nWork,someday,Standup\n");
+        assert_eq!(line, Some(2));
+        assert!(message.contains("someday"));
+    }
+}
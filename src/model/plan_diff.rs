@@ -0,0 +1,243 @@
+use crate::internal::*;
+
+/// A single routine-level difference between two revisions of a plan.
+///
+#[derive(Debug, Clone)]
+pub enum RoutineChange {
+    /// A routine only in the new plan
+    Added(Routine),
+    /// A routine only in the old plan
+    Removed(Routine),
+    /// One or more of the timing fields changed
+    Retimed {
+        /// Name of the routine
+        name: String,
+        /// The changed fields, as (field, before, after)
+        fields: Vec<(&'static str, Option<String>, Option<String>)>,
+    },
+    /// The activities for one day key changed.  The lists are compared in
+    /// order, like lines in a text diff, so an activity that moved is both
+    /// removed and added.
+    Activities {
+        /// Name of the routine
+        name: String,
+        /// The day key whose activities changed
+        day: DayOfWeek,
+        /// Activities added in the new plan
+        added: Vec<String>,
+        /// Activities removed from the old plan
+        removed: Vec<String>,
+    },
+}
+
+/// Computes the semantic differences between two plans.
+///
+/// Routines are matched by name.  Names may repeat within a plan (e.g. two
+/// "Work" blocks), so the Nth occurrence of a name in the old plan is
+/// matched against the Nth occurrence in the new plan.
+///
+pub fn diff_plans(old: &Plan, new: &Plan) -> Vec<RoutineChange> {
+    let old_keys = routine_keys(old);
+    let new_keys = routine_keys(new);
+    let mut changes = Vec::new();
+
+    for (key, routine) in &old_keys {
+        if !new_keys.iter().any(|(k, _)| k == key) {
+            changes.push(RoutineChange::Removed((*routine).clone()));
+        }
+    }
+
+    for (key, routine) in &new_keys {
+        let Some((_, prev)) = old_keys.iter().find(|(k, _)| k == key) else {
+            changes.push(RoutineChange::Added((*routine).clone()));
+            continue;
+        };
+
+        let fields: Vec<_> = prev
+            .timing_fields()
+            .into_iter()
+            .zip(routine.timing_fields())
+            .filter(|((_, before), (_, after))| before != after)
+            .map(|((field, before), (_, after))| (field, before, after))
+            .collect();
+        if !fields.is_empty() {
+            changes.push(RoutineChange::Retimed {
+                name: routine.name.clone(),
+                fields,
+            });
+        }
+
+        let mut days: Vec<&DayOfWeek> = prev.activities.keys().collect();
+        for day in routine.activities.keys() {
+            if !days.contains(&day) {
+                days.push(day);
+            }
+        }
+        days.sort();
+
+        for day in days {
+            let empty = Vec::new();
+            let before = prev.activities.get(day).unwrap_or(&empty);
+            let after = routine.activities.get(day).unwrap_or(&empty);
+
+            if before != after {
+                let (removed, added) = diff_lists(before, after);
+                changes.push(RoutineChange::Activities {
+                    name: routine.name.clone(),
+                    day: day.clone(),
+                    added,
+                    removed,
+                });
+            }
+        }
+    }
+
+    changes
+}
+
+/// Splits two lists into the items only in `before` and the items only in
+/// `after`, keeping the longest run of items common to both in order.
+fn diff_lists(before: &[String], after: &[String]) -> (Vec<String>, Vec<String>) {
+    // common[i][j] is the length of the longest common subsequence of
+    // before[i..] and after[j..]
+    let mut common = vec![vec![0; after.len() + 1]; before.len() + 1];
+    for i in (0..before.len()).rev() {
+        for j in (0..after.len()).rev() {
+            common[i][j] = match before[i] == after[j] {
+                true => common[i + 1][j + 1] + 1,
+                false => common[i + 1][j].max(common[i][j + 1]),
+            };
+        }
+    }
+
+    let (mut removed, mut added) = (Vec::new(), Vec::new());
+    let (mut i, mut j) = (0, 0);
+    while i < before.len() && j < after.len() {
+        if before[i] == after[j] {
+            i += 1;
+            j += 1;
+        } else if common[i + 1][j] >= common[i][j + 1] {
+            removed.push(before[i].clone());
+            i += 1;
+        } else {
+            added.push(after[j].clone());
+            j += 1;
+        }
+    }
+    removed.extend_from_slice(&before[i..]);
+    added.extend_from_slice(&after[j..]);
+    (removed, added)
+}
+
+/// Pairs each routine with a (name, occurrence) key used for matching.
+fn routine_keys(plan: &Plan) -> Vec<((String, usize), &Routine)> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    plan.daily_routine
+        .iter()
+        .map(|routine| {
+            let count = counts.entry(routine.name.as_str()).or_insert(0);
+            let key = (routine.name.clone(), *count);
+            *count += 1;
+            (key, routine)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAN: &str = "\
+daily_routine:
+  - name: Wake
+    start: 7am
+    duration: 30m
+  - name: Work
+    start: 9am
+    duration: 3h
+    activities:
+      default: [Email, Write, Review]
+";
+
+    fn diff(old: &str, new: &str) -> Vec<RoutineChange> {
+        diff_plans(
+            &Plan::from_yaml(old).unwrap(),
+            &Plan::from_yaml(new).unwrap(),
+        )
+    }
+
+    fn activity_changes(new: &str) -> (Vec<String>, Vec<String>) {
+        match diff(PLAN, new).as_slice() {
+            [RoutineChange::Activities { added, removed, .. }] => (added.clone(), removed.clone()),
+            changes => panic!("expected one activity change, got {:?}", changes),
+        }
+    }
+
+    #[test]
+    fn same_plan_has_no_changes() {
+        assert!(diff(PLAN, PLAN).is_empty());
+    }
+
+    #[test]
+    fn added_and_removed_routines() {
+        let new = PLAN.replace("  - name: Wake\n    start: 7am\n    duration: 30m\n", "")
+            + "  - name: Lunch\n    start: noon\n    duration: 1h\n";
+        match diff(PLAN, &new).as_slice() {
+            [RoutineChange::Removed(removed), RoutineChange::Added(added)] => {
+                assert_eq!(removed.name, "Wake");
+                assert_eq!(added.name, "Lunch");
+            }
+            changes => panic!("unexpected changes {:?}", changes),
+        }
+    }
+
+    #[test]
+    fn retimed_routines() {
+        let new = PLAN.replace("duration: 3h", "duration: 4h");
+        match diff(PLAN, &new).as_slice() {
+            [RoutineChange::Retimed { name, fields }] => {
+                assert_eq!(name, "Work");
+                assert_eq!(
+                    fields,
+                    &[("duration", Some("3h".to_string()), Some("4h".to_string()))]
+                );
+            }
+            changes => panic!("unexpected changes {:?}", changes),
+        }
+    }
+
+    #[test]
+    fn changed_activities() {
+        let (added, removed) =
+            activity_changes(&PLAN.replace("[Email, Write, Review]", "[Email, Plan, Review]"));
+        assert_eq!(added, ["Plan"]);
+        assert_eq!(removed, ["Write"]);
+    }
+
+    #[test]
+    fn reordered_activities() {
+        let (added, removed) =
+            activity_changes(&PLAN.replace("[Email, Write, Review]", "[Write, Review, Email]"));
+        assert_eq!(added, ["Email"]);
+        assert_eq!(removed, ["Email"]);
+    }
+
+    #[test]
+    fn duplicated_activities() {
+        let (added, removed) = activity_changes(
+            &PLAN.replace("[Email, Write, Review]", "[Email, Write, Review, Email]"),
+        );
+        assert_eq!(added, ["Email"]);
+        assert!(removed.is_empty());
+
+        let old = PLAN.replace("[Email, Write, Review]", "[Email, Email, Write]");
+        let new = PLAN.replace("[Email, Write, Review]", "[Email, Write]");
+        match diff(&old, &new).as_slice() {
+            [RoutineChange::Activities { added, removed, .. }] => {
+                assert!(added.is_empty());
+                assert_eq!(removed, &["Email"]);
+            }
+            changes => panic!("unexpected changes {:?}", changes),
+        }
+    }
+}
@@ -0,0 +1,1221 @@
+//! PlanDocument pairs the typed Plan with the original text of the plan.yaml
+//! file so that edits can be written back without discarding the user's
+//! comments and formatting.
+//!
+//! The document splits the `daily_routine` sequence into one text chunk per
+//! routine (including any comments immediately above it), and each routine
+//! into its fields, days, and activities. When the document is rendered,
+//! anything that was not modified is emitted verbatim: only the nodes that
+//! actually changed are re-rendered, in place, keeping their comments.
+//!
+use crate::internal::*;
+
+/// A plan along with the text of its plan.yaml file.
+#[derive(Debug, Clone)]
+pub struct PlanDocument {
+    /// Text up to and including the `daily_routine:` line
+    header: String,
+    /// Indentation of the `- ` sequence items
+    item_indent: usize,
+    nodes: Vec<RoutineNode>,
+    /// Text following the `daily_routine` sequence
+    trailer: String,
+}
+
+#[derive(Debug, Clone)]
+struct RoutineNode {
+    routine: Routine,
+    source: Option<RoutineSource>,
+}
+
+/// The source text of a routine, split into its fields so that a single
+/// field can be replaced without touching the rest of the text.
+#[derive(Debug, Clone)]
+struct RoutineSource {
+    /// The routine as originally parsed
+    original: Routine,
+    /// Full text of the routine, including the comments above it
+    text: String,
+    /// Comments and blank lines above the routine
+    leading: String,
+    /// The `- ` prefix of the first line, e.g. "  - "
+    dash: String,
+    /// Column at which the keys of the routine start
+    key_indent: usize,
+    /// The fields of the routine, with the dash replaced by spaces
+    fields: Vec<Block>,
+    /// Comments and blank lines after the last field
+    tail: String,
+}
+
+/// A line of YAML along with the comments above it and the more deeply
+/// indented lines that belong to it.
+#[derive(Debug, Clone)]
+struct Block {
+    key: String,
+    leading: String,
+    line: String,
+    children: String,
+}
+
+impl Block {
+    fn text(&self) -> String {
+        format!("{}{}{}", self.leading, self.line, self.children)
+    }
+
+    /// The value written on the key line itself (excluding any comment).
+    fn inline_value(&self) -> &str {
+        let rest = self.line.split_once(':').map_or("", |(_, rest)| rest);
+        split_comment(rest.trim_end_matches(['\r', '\n'])).0.trim()
+    }
+}
+
+impl PlanDocument {
+    //------------------------------------------------------------------------//
+    // Constructors
+    //------------------------------------------------------------------------//
+
+    /// Parses the text of a plan.yaml file.
+    ///
+    /// If the layout of the file is not one the document understands (for
+    /// example a flow-style sequence), the plan is still loaded but will be
+    /// fully re-rendered when saved.
+    pub fn parse(text: &str) -> Result<Self> {
+        let plan = Plan::from_yaml(text)?;
+        Ok(Self::split(text, &plan).unwrap_or_else(|| Self::from_plan(&plan)))
+    }
+
+    /// Creates a document with no source text for the given plan.
+    pub fn from_plan(plan: &Plan) -> Self {
+        Self {
+            header: "daily_routine:\n".to_string(),
+            item_indent: 2,
+            nodes: plan
+                .daily_routine
+                .iter()
+                .map(|routine| RoutineNode {
+                    routine: routine.clone(),
+                    source: None,
+                })
+                .collect(),
+            trailer: String::new(),
+        }
+    }
+
+    //------------------------------------------------------------------------//
+    // Accessors & mutators
+    //------------------------------------------------------------------------//
+
+    /// The plan as it currently stands, including any changes.
+    pub fn plan(&self) -> Plan {
+        Plan {
+            daily_routine: self.nodes.iter().map(|n| n.routine.clone()).collect(),
+        }
+    }
+
+    /// The routine at `index`, to be modified in place.
+    pub fn routine_mut(&mut self, index: usize) -> &mut Routine {
+        &mut self.nodes[index].routine
+    }
+
+    /// Inserts a routine at `index`, shifting later routines down.
+    pub fn insert_routine(&mut self, index: usize, routine: Routine) {
+        self.nodes.insert(
+            index,
+            RoutineNode {
+                routine,
+                source: None,
+            },
+        );
+    }
+
+    /// Removes and returns the routine at `index`, along with the comments
+    /// above it.
+    pub fn remove_routine(&mut self, index: usize) -> Routine {
+        self.nodes.remove(index).routine
+    }
+
+    /// Moves the routine at `from` to `to`, keeping its comments with it.
+    pub fn move_routine(&mut self, from: usize, to: usize) {
+        let node = self.nodes.remove(from);
+        self.nodes.insert(to, node);
+    }
+
+    /// Merges the routines of another plan into this one.  A routine with
+    /// the same name (ignoring case) takes the other routine's start and
+    /// duration, if it has them, and any activities it doesn't already
+    /// have.  Other routines with a start time are added before the first
+    /// routine that starts later in the day; the rest are added next to the routine
+    /// that precedes them in the other plan.
+    pub fn merge(&mut self, other: &Plan) {
+        let find = |nodes: &[RoutineNode], name: &str| {
+            nodes
+                .iter()
+                .position(|n| n.routine.name.eq_ignore_ascii_case(name))
+        };
+
+        let mut previous: Option<usize> = None;
+        for (i, incoming) in other.daily_routine.iter().enumerate() {
+            let index = match find(&self.nodes, &incoming.name) {
+                Some(index) => {
+                    merge_routine(&mut self.nodes[index].routine, incoming);
+                    index
+                }
+                None => {
+                    let start = incoming.start_minutes().ok().flatten();
+                    let index = match (start, previous) {
+                        (Some(start), _) => self.index_by_start(start),
+                        (None, Some(previous)) => previous + 1,
+                        // Before the next routine this plan already has, if
+                        // there's no previous one to follow
+                        (None, None) => other.daily_routine[i + 1..]
+                            .iter()
+                            .find_map(|r| find(&self.nodes, &r.name))
+                            .unwrap_or(self.nodes.len()),
+                    };
+                    self.insert_routine(index, incoming.clone());
+                    index
+                }
+            };
+            previous = Some(index);
+        }
+    }
+
+    /// Where a routine starting at the given time belongs: before the first
+    /// routine that starts later in the current schedule, or at the end if
+    /// there is none.
+    fn index_by_start(&self, start: u32) -> usize {
+        let plan = self.plan();
+        let starts: Vec<Option<u32>> = match Schedule::resolve(&plan, DayOfWeek::Default) {
+            Ok(schedule) => schedule.blocks.iter().map(|b| Some(b.start)).collect(),
+            // Fall back to the routines with a fixed start
+            Err(_) => plan
+                .daily_routine
+                .iter()
+                .map(|r| r.start_minutes().ok().flatten())
+                .collect(),
+        };
+        starts
+            .iter()
+            .position(|s| s.is_some_and(|s| s > start))
+            .unwrap_or(self.nodes.len())
+    }
+
+    /// Rewrites the plan in the canonical form of `Routine::canonical`, and
+    /// puts the fields of each routine and the days of its activities in
+    /// the standard order.  Comments move along with the field or day they
+    /// are above.
+    pub fn format(&mut self) {
+        for node in &mut self.nodes {
+            if let Some(source) = &node.source {
+                if let Some(sorted) = sort_routine(source, self.item_indent) {
+                    node.source = Some(sorted);
+                }
+            }
+            node.routine = node.routine.canonical();
+        }
+    }
+
+    //------------------------------------------------------------------------//
+    // Rendering
+    //------------------------------------------------------------------------//
+
+    /// The text of the plan.yaml file, with only the changed routines
+    /// re-rendered.
+    pub fn render(&self) -> String {
+        let mut out = self.header.clone();
+        for node in &self.nodes {
+            match &node.source {
+                Some(source) if source.original == node.routine => out.push_str(&source.text),
+                Some(source) => out.push_str(&self.render_changed(source, &node.routine)),
+                None => out.push_str(&render_routine(&node.routine, self.item_indent)),
+            }
+            if !out.ends_with('\n') {
+                out.push('\n');
+            }
+        }
+        out.push_str(&self.trailer);
+        out
+    }
+
+    /// Renders a routine that was modified, re-using as much of its source
+    /// text as possible.
+    fn render_changed(&self, source: &RoutineSource, routine: &Routine) -> String {
+        let patched = patch_routine(source, routine)
+            .or_else(|| patch_flow_routine(source, routine))
+            .filter(|text| {
+                // Never write text that doesn't read back as the intended routine
+                parse_routine(text, self.item_indent).as_ref() == Some(routine)
+            });
+        match patched {
+            Some(text) => format!("{}{}", source.leading, text),
+            None => format!(
+                "{}{}",
+                source.leading,
+                render_routine(routine, self.item_indent)
+            ),
+        }
+    }
+
+    //------------------------------------------------------------------------//
+    // Parsing helpers
+    //------------------------------------------------------------------------//
+
+    /// The 1-based line each routine of the plan starts on in `text`, for
+    /// error messages.  Empty if the layout isn't one the document
+    /// understands.
+    pub(crate) fn routine_lines(text: &str, plan: &Plan) -> Vec<usize> {
+        let Some(doc) = Self::split(text, plan) else {
+            return Vec::new();
+        };
+        let mut line = doc.header.matches('\n').count() + 1;
+        let mut lines = Vec::new();
+        for node in &doc.nodes {
+            let Some(source) = &node.source else {
+                return Vec::new();
+            };
+            lines.push(line + source.leading.matches('\n').count());
+            line += source.text.matches('\n').count();
+        }
+        lines
+    }
+
+    /// Splits the text into a header, one chunk per routine, and a trailer.
+    /// Returns None if the text does not have the expected block layout.
+    fn split(text: &str, plan: &Plan) -> Option<Self> {
+        let lines: Vec<&str> = text.split_inclusive('\n').collect();
+        let start = lines.iter().position(|line| {
+            line.strip_prefix("daily_routine:").is_some_and(|rest| {
+                let rest = rest.trim();
+                rest.is_empty() || rest.starts_with('#')
+            })
+        })?;
+
+        let header = lines[..=start].concat();
+        let item_indent = lines[start + 1..]
+            .iter()
+            .find(|line| !is_blank_or_comment(line))
+            .map(|line| indent_of(line))?;
+
+        let mut chunks: Vec<String> = Vec::new();
+        let mut pending = String::new();
+        let mut trailer = String::new();
+
+        for (i, line) in lines.iter().enumerate().skip(start + 1) {
+            if is_blank_or_comment(line) {
+                pending.push_str(line);
+                continue;
+            }
+
+            let indent = indent_of(line);
+            let is_item = indent == item_indent && line.trim_start().starts_with('-');
+            if is_item {
+                chunks.push(std::mem::take(&mut pending) + line);
+            } else if indent > item_indent && !chunks.is_empty() {
+                let chunk = chunks.last_mut().unwrap();
+                chunk.push_str(&std::mem::take(&mut pending));
+                chunk.push_str(line);
+            } else {
+                trailer = std::mem::take(&mut pending) + &lines[i..].concat();
+                break;
+            }
+        }
+        trailer.insert_str(0, &pending);
+
+        if chunks.len() != plan.daily_routine.len() {
+            return None;
+        }
+
+        let mut nodes = Vec::new();
+        for (chunk, routine) in chunks.into_iter().zip(&plan.daily_routine) {
+            // Double-check the chunk really is the text of this routine
+            if parse_routine(&chunk, item_indent).as_ref() != Some(routine) {
+                return None;
+            }
+            nodes.push(RoutineNode {
+                routine: routine.clone(),
+                source: Some(split_routine(chunk, routine)),
+            });
+        }
+
+        Some(Self {
+            header,
+            item_indent,
+            nodes,
+            trailer,
+        })
+    }
+}
+
+/// Merges `incoming` into `routine` as described in `PlanDocument::merge`.
+fn merge_routine(routine: &mut Routine, incoming: &Routine) {
+    if incoming.start.is_some() {
+        // A start time replaces any other way of placing the routine
+        routine.start = incoming.start.clone();
+        routine.after = None;
+    }
+    if incoming.duration.is_some() {
+        routine.duration = incoming.duration.clone();
+        routine.end = None;
+    }
+    for (day, activities) in &incoming.activities {
+        let list = routine.activities.entry(day.clone()).or_default();
+        for activity in activities {
+            if !list.contains(activity) {
+                list.push(activity.clone());
+            }
+        }
+    }
+}
+
+//===========================================================================//
+// Source splitting
+//===========================================================================//
+
+/// Splits the text of a single routine into its fields.  If the text can't
+/// be split (e.g. the routine is written in flow style), the routine has
+/// no fields and will be fully re-rendered if modified.
+fn split_routine(text: String, routine: &Routine) -> RoutineSource {
+    let leading: String = text
+        .split_inclusive('\n')
+        .take_while(|line| is_blank_or_comment(line))
+        .collect();
+    let body = &text[leading.len()..];
+
+    let mut source = RoutineSource {
+        original: routine.clone(),
+        text: text.clone(),
+        leading: leading.clone(),
+        dash: String::new(),
+        key_indent: 0,
+        fields: Vec::new(),
+        tail: String::new(),
+    };
+
+    // Replace the "- " on the first line with spaces so all fields are
+    // aligned at the same column
+    let first = body.split_inclusive('\n').next().unwrap_or("");
+    let after_dash = first.trim_start().strip_prefix('-').unwrap_or("");
+    let key_indent = first.len() - after_dash.trim_start().len();
+    if after_dash.trim().is_empty() || after_dash.trim_start().starts_with('{') {
+        return source;
+    }
+    let normalized = format!("{}{}", " ".repeat(key_indent), &body[key_indent..]);
+
+    let lines: Vec<&str> = normalized.split_inclusive('\n').collect();
+    if let Some((fields, tail)) = split_blocks(&lines) {
+        source.dash = body[..key_indent].to_string();
+        source.key_indent = key_indent;
+        source.fields = fields;
+        source.tail = tail;
+    }
+    source
+}
+
+/// Splits lines into blocks, each starting at a line with the same
+/// indentation as the first line.  Comments are attached to the block that
+/// follows them; any trailing comments are returned separately.
+fn split_blocks(lines: &[&str]) -> Option<(Vec<Block>, String)> {
+    let mut blocks: Vec<Block> = Vec::new();
+    let mut pending = String::new();
+    let mut block_indent = None;
+
+    for line in lines {
+        if is_blank_or_comment(line) {
+            pending.push_str(line);
+            continue;
+        }
+
+        let indent = indent_of(line);
+        let block_indent = *block_indent.get_or_insert(indent);
+        if indent == block_indent {
+            let trimmed = line.trim_start();
+            let key = if trimmed.starts_with('-') {
+                String::new()
+            } else {
+                trimmed.split_once(':')?.0.trim().to_string()
+            };
+            blocks.push(Block {
+                key,
+                leading: std::mem::take(&mut pending),
+                line: line.to_string(),
+                children: String::new(),
+            });
+        } else if indent > block_indent {
+            let block = blocks.last_mut()?;
+            block.children.push_str(&std::mem::take(&mut pending));
+            block.children.push_str(line);
+        } else {
+            return None;
+        }
+    }
+    Some((blocks, pending))
+}
+
+/// Parses the text of a single sequence item back into a routine.
+fn parse_routine(text: &str, item_indent: usize) -> Option<Routine> {
+    let parsed: Vec<Routine> = serde_yaml::from_str(&dedent(text, item_indent)).ok()?;
+    match parsed.as_slice() {
+        [routine] => Some(Routine::new(routine.clone())),
+        _ => None,
+    }
+}
+
+//===========================================================================//
+// Formatting
+//===========================================================================//
+
+/// Rearranges the source text of a routine so its fields and activity days
+/// are in the standard order, and rewrites start and end times whose text
+/// isn't already lower-case.  Returns None if the routine can't be split
+/// into fields or the result doesn't read back as the same routine.
+fn sort_routine(source: &RoutineSource, item_indent: usize) -> Option<RoutineSource> {
+    if source.fields.is_empty() {
+        return None;
+    }
+
+    let mut fields = source.fields.clone();
+    fields.sort_by_key(|block| field_rank(&block.key));
+    for block in &mut fields {
+        if block.key == "activities" && block.inline_value().is_empty() {
+            block.children = sort_days(&block.children)?;
+        }
+
+        // The parsed times are already normalized, so compare with the text
+        let time = match block.key.as_str() {
+            "start" => &source.original.start,
+            "end" => &source.original.end,
+            _ => &None,
+        };
+        if let Some(time) = time.as_deref() {
+            if block.inline_value() != yaml_scalar(time) {
+                let key_line = Block {
+                    leading: String::new(),
+                    children: String::new(),
+                    ..block.clone()
+                };
+                block.line = replace_value(&key_line, time);
+                block.children.clear();
+            }
+        }
+    }
+
+    let sorted = RoutineSource {
+        fields,
+        ..source.clone()
+    };
+    let text = format!(
+        "{}{}",
+        source.leading,
+        patch_routine(&sorted, &source.original)?
+    );
+    if parse_routine(&text, item_indent).as_ref() != Some(&source.original) {
+        return None;
+    }
+    Some(split_routine(text, &source.original))
+}
+
+/// Sorts the days in the text of an activities map by the standard day
+/// order.  Returns None if a day can't be parsed.
+fn sort_days(children: &str) -> Option<String> {
+    let lines: Vec<&str> = children.split_inclusive('\n').collect();
+    let (days, tail) = split_blocks(&lines)?;
+    let mut ranked: Vec<(usize, Block)> = days
+        .into_iter()
+        .map(|day| Some((day_rank(&day.key.parse().ok()?), day)))
+        .collect::<Option<_>>()?;
+    ranked.sort_by_key(|(rank, _)| *rank);
+
+    let mut out: String = ranked.iter().map(|(_, day)| day.text()).collect();
+    out.push_str(&tail);
+    Some(out)
+}
+
+/// Position of a routine field in the standard order, with keys this
+/// version of guidebook-plan doesn't know about kept before the activities.
+fn field_rank(key: &str) -> usize {
+    match ["name", "start", "after", "end", "duration"]
+        .iter()
+        .position(|k| *k == key)
+    {
+        Some(rank) => rank,
+        None if key == "activities" => 6,
+        None => 5,
+    }
+}
+
+//===========================================================================//
+// Patching
+//===========================================================================//
+
+/// The scalar fields of a routine, in the order they are written.
+fn scalar_fields(routine: &Routine) -> Vec<(&'static str, Option<String>)> {
+    let mut fields = vec![("name", Some(routine.name.clone()))];
+    fields.extend(routine.timing_fields());
+    fields
+}
+
+/// Re-renders only the fields of the routine that differ from the source.
+fn patch_routine(source: &RoutineSource, routine: &Routine) -> Option<String> {
+    if source.fields.is_empty() {
+        return None;
+    }
+
+    let pad = " ".repeat(source.key_indent);
+    let old_scalars = scalar_fields(&source.original);
+    let new_scalars = scalar_fields(routine);
+
+    // Each piece of output text is paired with the key it belongs to
+    let mut out: Vec<(&str, String)> = Vec::new();
+
+    for block in &source.fields {
+        if let Some(i) = new_scalars.iter().position(|(key, _)| *key == block.key) {
+            let (key, new_value) = &new_scalars[i];
+            match new_value {
+                None => {}
+                Some(_) if *new_value == old_scalars[i].1 => out.push((key, block.text())),
+                Some(value) => out.push((key, replace_value(block, value))),
+            }
+        } else if block.key == "activities" {
+            let text = if routine.activities == source.original.activities {
+                block.text()
+            } else if routine.activities.is_empty() {
+                // Drop the field, but not the comments above it
+                block.leading.clone()
+            } else {
+                patch_activities(
+                    block,
+                    &source.original.activities,
+                    &routine.activities,
+                    source.key_indent,
+                )?
+            };
+            out.push(("activities", text));
+        } else {
+            // A key this version of guidebook-plan doesn't know about
+            out.push(("", block.text()));
+        }
+    }
+
+    // Add any fields that weren't in the source right after the field that
+    // precedes them in the standard order
+    for (i, (key, value)) in new_scalars.iter().enumerate() {
+        let Some(value) = value else {
+            continue;
+        };
+        if out.iter().any(|(k, _)| k == key) {
+            continue;
+        }
+        let insert_at = out
+            .iter()
+            .rposition(|(k, _)| new_scalars[..i].iter().any(|(prev, _)| prev == k))
+            .map_or(0, |p| p + 1);
+        out.insert(
+            insert_at,
+            (key, format!("{}{}: {}\n", pad, key, yaml_scalar(value))),
+        );
+    }
+    if !out.iter().any(|(k, _)| *k == "activities") && !routine.activities.is_empty() {
+        out.push((
+            "activities",
+            render_activities(&routine.activities, source.key_indent),
+        ));
+    }
+
+    // Put the "- " back onto the first line
+    let mut text: String = out.into_iter().map(|(_, text)| text).collect();
+    let first_line = text
+        .split_inclusive('\n')
+        .take_while(|line| is_blank_or_comment(line))
+        .map(str::len)
+        .sum::<usize>();
+    if text.len() < first_line + source.key_indent {
+        return None;
+    }
+    text.replace_range(first_line..first_line + source.key_indent, &source.dash);
+    text.push_str(&source.tail);
+    Some(text)
+}
+
+/// Replaces the value on a `key: value` line, keeping any trailing comment.
+fn replace_value(block: &Block, value: &str) -> String {
+    let pad = " ".repeat(indent_of(&block.line));
+    if !block.children.is_empty() {
+        // A multi-line value; replace it entirely
+        return format!(
+            "{}{}{}: {}\n",
+            block.leading,
+            pad,
+            block.key,
+            yaml_scalar(value)
+        );
+    }
+
+    let line = block.line.trim_end_matches(['\r', '\n']);
+    let (prefix, rest) = line.split_once(':').unwrap_or((line, ""));
+    let comment = split_comment(rest).1;
+    format!(
+        "{}{}: {}{}\n",
+        block.leading,
+        prefix,
+        yaml_scalar(value),
+        comment
+    )
+}
+
+/// Re-renders only the days of the activities map that changed.
+fn patch_activities(
+    block: &Block,
+    old: &HashMap<DayOfWeek, Vec<String>>,
+    new: &HashMap<DayOfWeek, Vec<String>>,
+    key_indent: usize,
+) -> Option<String> {
+    if new.is_empty() || !block.inline_value().is_empty() {
+        return Some(format!(
+            "{}{}",
+            block.leading,
+            render_activities(new, key_indent)
+        ));
+    }
+
+    let lines: Vec<&str> = block.children.split_inclusive('\n').collect();
+    let (days, tail) = split_blocks(&lines)?;
+    let parsed_days: Vec<DayOfWeek> = days
+        .iter()
+        .map(|day| day.key.parse().ok())
+        .collect::<Option<_>>()?;
+    if parsed_days.len() != old.len() {
+        return None;
+    }
+
+    let day_indent = days
+        .first()
+        .map_or(key_indent + 2, |day| indent_of(&day.line));
+    let item_indent = days
+        .iter()
+        .find_map(|day| day.children.lines().find(|l| !is_blank_or_comment(l)))
+        .map_or(day_indent + 2, indent_of);
+
+    // Existing days stay where they are; new days are slotted in by their
+    // position in the standard day order
+    let mut order: Vec<(DayOfWeek, Option<&Block>)> = parsed_days
+        .iter()
+        .zip(&days)
+        .filter(|(day, _)| new.contains_key(day))
+        .map(|(day, block)| (day.clone(), Some(block)))
+        .collect();
+    for day in DayOfWeek::all() {
+        if new.contains_key(day) && !parsed_days.contains(day) {
+            let rank = day_rank(day);
+            let at = order
+                .iter()
+                .position(|(d, _)| day_rank(d) > rank)
+                .unwrap_or(order.len());
+            order.insert(at, (day.clone(), None));
+        }
+    }
+
+    let mut out = format!("{}{}", block.leading, block.line);
+    for (day, day_block) in order {
+        let new_list = &new[&day];
+        match day_block {
+            Some(day_block) if old.get(&day) == Some(new_list) => {
+                out.push_str(&day_block.text());
+            }
+            Some(day_block) => {
+                out.push_str(&patch_day(day_block, &old[&day], new_list, item_indent));
+            }
+            None => out.push_str(&render_day(&day, new_list, day_indent, item_indent)),
+        }
+    }
+    out.push_str(&tail);
+    Some(out)
+}
+
+/// Re-renders the list of activities for a day, keeping the text of any
+/// activity that is still present.
+fn patch_day(block: &Block, old: &[String], new: &[String], item_indent: usize) -> String {
+    let lines: Vec<&str> = block.children.split_inclusive('\n').collect();
+    let items = match split_blocks(&lines) {
+        Some((items, tail)) if block.inline_value().is_empty() && items.len() == old.len() => {
+            Some((items, tail))
+        }
+        _ => None,
+    };
+    let Some((items, tail)) = items else {
+        let indent = indent_of(&block.line);
+        return format!(
+            "{}{}",
+            block.leading,
+            render_day(&block.key.parse().unwrap(), new, indent, item_indent)
+        );
+    };
+
+    let mut used = vec![false; old.len()];
+    let mut out = format!("{}{}", block.leading, block.line);
+    for activity in new {
+        let reuse = old
+            .iter()
+            .enumerate()
+            .position(|(i, a)| !used[i] && a == activity);
+        match reuse {
+            Some(i) => {
+                used[i] = true;
+                out.push_str(&items[i].text());
+            }
+            None => out.push_str(&format!(
+                "{}- {}\n",
+                " ".repeat(item_indent),
+                yaml_scalar(activity)
+            )),
+        }
+    }
+    out.push_str(&tail);
+    out
+}
+
+/// Re-renders a routine written as a flow mapping on a single line, e.g.
+/// `- {name: Walk, duration: 30m}`, in the same style, keeping any comment
+/// at the end of the line.
+fn patch_flow_routine(source: &RoutineSource, routine: &Routine) -> Option<String> {
+    let body = source.text[source.leading.len()..].trim_end_matches(['\r', '\n']);
+    if body.contains('\n') {
+        return None;
+    }
+    let (prefix, mapping) = body.split_once('{')?;
+
+    // Find the closing brace, skipping any in quoted values
+    let mut depth = 1;
+    let mut quote = None;
+    let mut close = None;
+    for (i, c) in mapping.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '{') => depth += 1,
+            (None, '}') => {
+                depth -= 1;
+                if depth == 0 {
+                    close = Some(i);
+                    break;
+                }
+            }
+            _ => {}
+        }
+    }
+    let comment = &mapping[close? + 1..];
+    Some(format!(
+        "{}{}{}\n",
+        prefix,
+        render_flow_mapping(routine),
+        comment
+    ))
+}
+
+//===========================================================================//
+// Rendering helpers
+//===========================================================================//
+
+/// Renders a routine as a flow mapping, e.g. `{name: Walk, duration: 30m}`.
+fn render_flow_mapping(routine: &Routine) -> String {
+    let mut fields: Vec<String> = scalar_fields(routine)
+        .into_iter()
+        .filter_map(|(key, value)| Some(format!("{}: {}", key, flow_scalar(&value?))))
+        .collect();
+    if !routine.activities.is_empty() {
+        let days: Vec<String> = DayOfWeek::all()
+            .iter()
+            .filter_map(|day| {
+                let list = routine.activities.get(day)?;
+                let items: Vec<String> = list.iter().map(|a| flow_scalar(a)).collect();
+                Some(format!("{}: [{}]", day.key(), items.join(", ")))
+            })
+            .collect();
+        fields.push(format!("activities: {{{}}}", days.join(", ")));
+    }
+    format!("{{{}}}", fields.join(", "))
+}
+
+/// Renders a single routine as a block-style sequence item.
+fn render_routine(routine: &Routine, indent: usize) -> String {
+    let pad = " ".repeat(indent);
+    let mut out = String::new();
+    for (key, value) in scalar_fields(routine) {
+        let Some(value) = value else {
+            continue;
+        };
+        let prefix = if out.is_empty() { "- " } else { "  " };
+        out.push_str(&format!(
+            "{}{}{}: {}\n",
+            pad,
+            prefix,
+            key,
+            yaml_scalar(&value)
+        ));
+    }
+    if !routine.activities.is_empty() {
+        out.push_str(&render_activities(&routine.activities, indent + 2));
+    }
+    out
+}
+
+fn render_activities(activities: &HashMap<DayOfWeek, Vec<String>>, indent: usize) -> String {
+    let pad = " ".repeat(indent);
+    if activities.is_empty() {
+        return format!("{}activities: {{}}\n", pad);
+    }
+
+    let mut out = format!("{}activities:\n", pad);
+    for day in DayOfWeek::all() {
+        if let Some(list) = activities.get(day) {
+            out.push_str(&render_day(day, list, indent + 2, indent + 4));
+        }
+    }
+    out
+}
+
+fn render_day(day: &DayOfWeek, activities: &[String], indent: usize, item_indent: usize) -> String {
+    let mut out = format!("{}{}:\n", " ".repeat(indent), day.key());
+    for activity in activities {
+        out.push_str(&format!(
+            "{}- {}\n",
+            " ".repeat(item_indent),
+            yaml_scalar(activity)
+        ));
+    }
+    out
+}
+
+/// Formats a string as a YAML scalar, quoting it only when necessary.
+/// Control characters such as newlines are escaped in double quotes, as
+/// serde_yaml would write a block scalar that doesn't fit on one line.
+fn yaml_scalar(value: &str) -> String {
+    if value.contains(char::is_control) {
+        return serde_json::to_string(value).unwrap_or_else(|_| format!("{:?}", value));
+    }
+    serde_yaml::to_string(value)
+        .map(|s| s.trim_end().to_string())
+        .unwrap_or_else(|_| format!("{:?}", value))
+}
+
+/// Formats a string as a scalar inside a flow collection, where commas and
+/// brackets also need quoting.
+fn flow_scalar(value: &str) -> String {
+    if value.contains([',', '[', ']', '{', '}']) {
+        serde_json::to_string(value).unwrap_or_else(|_| format!("{:?}", value))
+    } else {
+        yaml_scalar(value)
+    }
+}
+
+//===========================================================================//
+// Text utilities
+//===========================================================================//
+
+fn day_rank(day: &DayOfWeek) -> usize {
+    DayOfWeek::all().iter().position(|d| d == day).unwrap_or(0)
+}
+
+fn is_blank_or_comment(line: &str) -> bool {
+    let trimmed = line.trim();
+    trimmed.is_empty() || trimmed.starts_with('#')
+}
+
+fn indent_of(line: &str) -> usize {
+    line.len() - line.trim_start_matches(' ').len()
+}
+
+fn dedent(text: &str, amount: usize) -> String {
+    text.split_inclusive('\n')
+        .map(|line| {
+            let strip = indent_of(line).min(amount);
+            &line[strip..]
+        })
+        .collect()
+}
+
+/// Splits the text following a `key:` into the value and a trailing
+/// comment (including the whitespace before the `#`).
+fn split_comment(rest: &str) -> (&str, &str) {
+    let value_start = rest.len() - rest.trim_start().len();
+    let value = &rest[value_start..];
+
+    // Don't look for a comment inside a quoted value
+    let search_from = match value.chars().next() {
+        Some(quote @ ('"' | '\'')) => value[1..].find(quote).map_or(0, |i| i + 2),
+        _ => 0,
+    };
+
+    match value[search_from..].find(" #") {
+        Some(i) => {
+            let at = value_start + search_from + i;
+            let value_end = rest[..at].trim_end().len();
+            (&rest[..value_end], &rest[value_end..])
+        }
+        None => (rest, ""),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAN: &str = "# My plan
+daily_routine:
+  # Mornings are slow
+  - name: Wake   # the alarm
+    start: 7:00 am
+    duration: 30m
+    activities:
+      default:
+        - Stretch
+      # Mondays
+      mon:
+        - Run
+        - 'Coffee: black'
+
+  - duration: 3h  # keys in an unusual order
+    name: Work
+    activities: {}
+  - {name: Lunch, start: noon, duration: 1h}  # flow
+# trailing comment
+";
+
+    /// Applies an edit to the parsed document and renders it back.
+    fn edit(text: &str, f: impl FnOnce(&mut PlanDocument)) -> String {
+        let mut doc = PlanDocument::parse(text).unwrap();
+        f(&mut doc);
+        let rendered = doc.render();
+        // Whatever is written must read back as the edited plan
+        assert_eq!(Plan::from_yaml(&rendered).unwrap(), doc.plan());
+        rendered
+    }
+
+    #[test]
+    fn unchanged_document_renders_verbatim() {
+        assert_eq!(edit(PLAN, |_| {}), PLAN);
+    }
+
+    #[test]
+    fn changed_field_keeps_comments() {
+        let text = edit(PLAN, |doc| {
+            doc.routine_mut(0).duration = Some("45m".to_string());
+        });
+        assert_eq!(text, PLAN.replace("duration: 30m", "duration: 45m"));
+    }
+
+    #[test]
+    fn key_order_and_trailing_comments_are_kept() {
+        let text = edit(PLAN, |doc| {
+            doc.routine_mut(1).duration = Some("4h".to_string());
+        });
+        assert_eq!(
+            text,
+            PLAN.replace(
+                "duration: 3h  # keys in an unusual order",
+                "duration: 4h  # keys in an unusual order"
+            )
+        );
+    }
+
+    #[test]
+    fn new_field_goes_in_the_standard_order() {
+        let text = edit(PLAN, |doc| {
+            doc.routine_mut(1).start = Some("9am".to_string());
+        });
+        assert_eq!(
+            text,
+            PLAN.replace("    name: Work\n", "    name: Work\n    start: 9am\n")
+        );
+    }
+
+    #[test]
+    fn rename_only_changes_the_name() {
+        let text = edit(PLAN, |doc| {
+            doc.routine_mut(0).name = "Rise".to_string();
+        });
+        assert_eq!(
+            text,
+            PLAN.replace("name: Wake   # the alarm", "name: Rise   # the alarm")
+        );
+    }
+
+    #[test]
+    fn values_are_quoted_when_needed() {
+        let text = edit(PLAN, |doc| {
+            doc.routine_mut(0).name = "Wake: early # really".to_string();
+        });
+        assert!(text.contains("  - name: 'Wake: early # really'   # the alarm\n"));
+    }
+
+    #[test]
+    fn values_with_newlines_stay_on_one_line() {
+        let text = edit(PLAN, |doc| {
+            doc.routine_mut(0).name = "Wake\nup".to_string();
+            let activities = &mut doc.routine_mut(0).activities;
+            activities.insert(DayOfWeek::Default, vec!["Stretch\n\tslowly".to_string()]);
+            doc.routine_mut(2).name = "Lunch\n".to_string();
+        });
+        assert!(text.contains("  - name: \"Wake\\nup\"   # the alarm\n"));
+        assert!(text.contains("        - \"Stretch\\n\\tslowly\"\n"));
+        assert!(text.contains("  - {name: \"Lunch\\n\", start: noon"));
+        assert_eq!(text.lines().count(), PLAN.lines().count());
+    }
+
+    #[test]
+    fn flow_style_routines_stay_on_one_line() {
+        let text = edit(PLAN, |doc| {
+            doc.routine_mut(2).duration = Some("90m".to_string());
+        });
+        assert_eq!(
+            text,
+            PLAN.replace(
+                "{name: Lunch, start: noon, duration: 1h}",
+                "{name: Lunch, start: noon, duration: 90m}"
+            )
+        );
+
+        let text = edit(PLAN, |doc| {
+            let activities = &mut doc.routine_mut(2).activities;
+            activities.insert(DayOfWeek::Default, vec!["Eat, slowly".to_string()]);
+        });
+        assert!(text.contains(
+            "  - {name: Lunch, start: noon, duration: 1h, activities: {default: [\"Eat, slowly\"]}}  # flow\n"
+        ));
+    }
+
+    #[test]
+    fn activities_can_be_added_and_removed() {
+        let text = edit(PLAN, |doc| {
+            let activities = &mut doc.routine_mut(0).activities;
+            activities
+                .get_mut(&DayOfWeek::Mon)
+                .unwrap()
+                .push("Read".to_string());
+            activities.insert(DayOfWeek::Weekdays, vec!["Plan the day".to_string()]);
+        });
+        assert_eq!(
+            text,
+            PLAN.replace(
+                "        - Stretch\n",
+                "        - Stretch\n      weekdays:\n        - Plan the day\n"
+            )
+            .replace(
+                "        - 'Coffee: black'\n",
+                "        - 'Coffee: black'\n        - Read\n"
+            )
+        );
+
+        let text = edit(PLAN, |doc| {
+            let activities = &mut doc.routine_mut(0).activities;
+            activities.get_mut(&DayOfWeek::Mon).unwrap().remove(0);
+            activities.remove(&DayOfWeek::Default);
+        });
+        assert_eq!(
+            text,
+            PLAN.replace("      default:\n        - Stretch\n", "")
+                .replace("        - Run\n", "")
+        );
+    }
+
+    #[test]
+    fn removing_every_activity_drops_the_field() {
+        let text = edit(PLAN, |doc| {
+            doc.routine_mut(0).activities.clear();
+        });
+        assert!(!text.contains("Stretch"));
+        assert!(!text.contains("activities: {}\n    duration: 3h"));
+        assert!(text.contains("    duration: 30m\n\n  - duration: 3h"));
+    }
+
+    #[test]
+    fn inserted_and_removed_routines() {
+        let text = edit(PLAN, |doc| {
+            doc.remove_routine(1);
+            doc.insert_routine(
+                1,
+                Routine::new(Routine {
+                    name: "Walk".to_string(),
+                    start: None,
+                    after: Some("Wake + 15m".to_string()),
+                    end: None,
+                    duration: Some("30m".to_string()),
+                    activities: HashMap::new(),
+                }),
+            );
+        });
+        assert_eq!(
+            text,
+            PLAN.replace(
+                // The blank line above a routine goes with it
+                "\n  - duration: 3h  # keys in an unusual order\n    name: Work\n    activities: {}\n",
+                "  - name: Walk\n    after: Wake + 15m\n    duration: 30m\n"
+            )
+        );
+    }
+
+    const MERGE_PLAN: &str = "daily_routine:
+  - name: Morning
+    start: 7am
+    duration: 1h
+  - name: Work
+    duration: 3h
+    activities:
+      default:
+        - Inbox
+  - name: Lunch
+    start: noon
+    duration: 1h
+";
+
+    fn merged(csv: &str) -> Plan {
+        let mut doc = PlanDocument::parse(MERGE_PLAN).unwrap();
+        doc.merge(&Plan::from_csv(csv).unwrap());
+        let plan = Plan::from_yaml(&doc.render()).unwrap();
+        assert_eq!(plan, doc.plan());
+        plan
+    }
+
+    fn names(plan: &Plan) -> Vec<&str> {
+        plan.daily_routine.iter().map(|r| r.name.as_str()).collect()
+    }
+
+    #[test]
+    fn routines_with_a_start_go_in_time_order() {
+        let plan = merged("name,start,duration\nGym+Swim,6am,1h\nWalk,10:30am,30m\n");
+        assert_eq!(
+            names(&plan),
+            vec!["Gym+Swim", "Morning", "Work", "Walk", "Lunch"]
+        );
+    }
+
+    #[test]
+    fn routines_without_a_start_follow_their_neighbours() {
+        let plan = merged("name,duration\nLunch,1h\nNap,20m\nWrap up,30m\n");
+        assert_eq!(
+            names(&plan),
+            vec!["Morning", "Work", "Lunch", "Nap", "Wrap up"]
+        );
+
+        let plan = merged("name,duration\nCoffee,10m\nWork,3h\n");
+        assert_eq!(names(&plan), vec!["Morning", "Coffee", "Work", "Lunch"]);
+    }
+
+    #[test]
+    fn existing_routines_are_updated() {
+        let plan = merged(
+            "name,start,duration,day,activity
+work,,2h,,Inbox
+work,,,mon,Standup
+morning,6:30am,,,
+",
+        );
+        assert_eq!(names(&plan), vec!["Morning", "Work", "Lunch"]);
+        assert_eq!(plan.daily_routine[0].start.as_deref(), Some("6:30am"));
+        let work = &plan.daily_routine[1];
+        assert_eq!(work.duration.as_deref(), Some("2h"));
+        assert_eq!(work.activities[&DayOfWeek::Default], vec!["Inbox"]);
+        assert_eq!(work.activities[&DayOfWeek::Mon], vec!["Standup"]);
+    }
+}
@@ -0,0 +1,124 @@
+//! JSON Schema for plan.yaml, so YAML language servers can autocomplete
+//! and validate the file while it's edited.
+//!
+//! The schema is derived from [`Plan`], [`Routine`] and [`DayOfWeek`].
+//! Times and durations are checked with patterns that follow the grammar
+//! in `time_grammar`; the patterns are looser than the parsers (e.g. they
+//! don't check that minutes are below 60), so `guidebook-plan show` remains
+//! the final word on whether a plan is valid.
+//!
+//! The schema is versioned with the plan format: a change to the format
+//! bumps [`PLAN_FORMAT_VERSION`] and publishes a new schema file next to
+//! the old ones.
+//!
+use crate::internal::*;
+
+/// Version of the plan.yaml format described by the schema.
+pub const PLAN_FORMAT_VERSION: u32 = 1;
+
+/// Pattern for the times of day accepted by `parse_time_of_day`.
+pub(crate) const TIME_OF_DAY_PATTERN: &str =
+    r"^\s*(?:noon|midnight|(?:\d{1,4}|\d{1,2}(?::\d{2}){1,2})\s*(?:am|pm|a\.m\.|p\.m\.|a|p)?)\s*$";
+
+/// Pattern for the durations accepted by `parse_duration`, plus "fill".
+pub(crate) const DURATION_PATTERN: &str = r"^\s*(?:fill|\d+(?::\d{2}){1,2}|(?:(?:\d+\.?\d*|\.\d+)\s*(?:hours?|hrs?|h|minutes?|mins?|m|seconds?|secs?|s)\s*)+)\s*$";
+
+/// The URL the schema for the current plan format is published at.
+pub fn plan_schema_url() -> String {
+    format!(
+        "https://raw.githubusercontent.com/raiment-studios/guidebook-plan/main/schemas/plan-v{}.json",
+        PLAN_FORMAT_VERSION
+    )
+}
+
+/// The modeline that points YAML language servers at the schema, for the
+/// first line of a plan.yaml file.
+pub fn plan_schema_modeline() -> String {
+    format!("# yaml-language-server: $schema={}", plan_schema_url())
+}
+
+impl Plan {
+    /// The JSON Schema for plan.yaml.
+    pub fn json_schema() -> serde_json::Value {
+        let mut schema = schemars::schema_for!(Plan);
+        schema.insert("$id".to_string(), plan_schema_url().into());
+        schema.insert(
+            "title".to_string(),
+            format!("guidebook-plan plan.yaml (format v{})", PLAN_FORMAT_VERSION).into(),
+        );
+        schema.to_value()
+    }
+}
+
+/// Lets a time field be a YAML integer too: an unquoted hour such as
+/// `start: 7` reaches serde as a string, but editors see a number.
+pub(crate) fn allow_bare_hour(schema: &mut schemars::Schema) {
+    if let Some(serde_json::Value::Array(types)) = schema.get_mut("type") {
+        types.insert(1, "integer".into());
+    }
+    schema.insert("minimum".to_string(), 0.into());
+}
+
+/// Makes a pattern match regardless of case.  JSON Schema patterns have no
+/// flags, so each letter outside an escape becomes a class with both cases.
+pub(crate) fn ignore_case(pattern: &str) -> String {
+    let mut out = String::new();
+    let mut escaped = false;
+    for c in pattern.chars() {
+        if !escaped && c.is_ascii_alphabetic() {
+            out.push_str(&format!(
+                "[{}{}]",
+                c.to_ascii_lowercase(),
+                c.to_ascii_uppercase()
+            ));
+        } else {
+            out.push(c);
+        }
+        escaped = !escaped && c == '\\';
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::time_grammar::tests::{DURATIONS, TIMES};
+    use regex::Regex;
+
+    #[test]
+    fn published_schema_is_up_to_date() {
+        let published: serde_json::Value =
+            serde_json::from_str(include_str!("../../schemas/plan-v1.json")).unwrap();
+        assert_eq!(
+            published,
+            Plan::json_schema(),
+            "schemas/plan-v{} differs from Plan::json_schema(); if the format changed, \
+             bump PLAN_FORMAT_VERSION and publish a new schema",
+            PLAN_FORMAT_VERSION
+        );
+    }
+
+    #[test]
+    fn patterns_accept_what_the_parsers_accept() {
+        let time = Regex::new(&ignore_case(TIME_OF_DAY_PATTERN)).unwrap();
+        for input in TIMES {
+            assert!(time.is_match(input), "{:?}", input);
+        }
+        let duration = Regex::new(&ignore_case(DURATION_PATTERN)).unwrap();
+        for input in DURATIONS.iter().chain(&["fill"]) {
+            assert!(duration.is_match(input), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn patterns_reject_other_text() {
+        let time = Regex::new(&ignore_case(TIME_OF_DAY_PATTERN)).unwrap();
+        for input in ["", "7 xm", "lunchtime", "7:5"] {
+            assert!(!time.is_match(input), "{:?}", input);
+        }
+        let duration = Regex::new(&ignore_case(DURATION_PATTERN)).unwrap();
+        for input in ["", "abc", "1 fortnight", "1:5"] {
+            assert!(!duration.is_match(input), "{:?}", input);
+        }
+    }
+}
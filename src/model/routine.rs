@@ -1,86 +1,241 @@
 use crate::internal::*;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A block of the day, with its timing and its activities for each day.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct Routine {
+    /// Name of the routine, e.g. "Lunch"
     pub name: String,
+    /// Time of day the routine starts, e.g. "7:30 am" or "13:00"
+    #[schemars(pattern(ignore_case(TIME_OF_DAY_PATTERN)), transform = allow_bare_hour)]
     pub start: Option<String>,
-    pub duration: String,
+    /// Start relative to the end of another routine, e.g. "Lunch + 15m"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after: Option<String>,
+    /// An end time, as an alternative to specifying the duration
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(pattern(ignore_case(TIME_OF_DAY_PATTERN)), transform = allow_bare_hour)]
+    pub end: Option<String>,
+    /// How long the routine lasts, e.g. "45m" or "1h30m", or "fill"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(pattern(ignore_case(DURATION_PATTERN)))]
+    pub duration: Option<String>,
+    /// Activities for each day key, e.g. "default", "weekdays" or "mon"
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     pub activities: HashMap<DayOfWeek, Vec<String>>,
 }
 
 impl Routine {
+    /// Normalizes a routine as read from plan.yaml.
     pub fn new(mut data: Routine) -> Self {
-        // Normalize the start time
-        if let Some(ref mut start) = data.start {
-            *start = start.trim().to_lowercase();
+        // Normalize the start and end times
+        for time in [&mut data.start, &mut data.end].into_iter().flatten() {
+            *time = time.trim().to_lowercase();
         }
         data
     }
 
+    /// The routine in the canonical form written by `guidebook-plan fmt`:
+    /// text is trimmed, times are lower-cased as in `Routine::new`, and
+    /// durations, including the offset of an `after` anchor, are written
+    /// as e.g. "1h30m".  Values that don't parse are only trimmed.
+    pub fn canonical(&self) -> Routine {
+        let mut routine = Routine::new(self.clone());
+        routine.name = routine.name.trim().to_string();
+        routine.duration = routine.duration.map(|d| canonical_duration_text(&d));
+        routine.after = match self.after_anchor() {
+            Ok(Some((name, 0))) => Some(name),
+            Ok(Some((name, offset))) => {
+                Some(format!("{} + {}", name, canonical_duration(offset * 60)))
+            }
+            _ => routine.after.map(|after| after.trim().to_string()),
+        };
+        for activities in routine.activities.values_mut() {
+            for activity in activities {
+                *activity = activity.trim().to_string();
+            }
+        }
+        routine
+    }
+
+    /// Checks that the timing fields can be parsed and agree with each
+    /// other.  The `after` anchor, like the other constraints between
+    /// routines, is checked by `Schedule`, which reports it as a conflict.
+    pub fn validate(&self) -> Result<()> {
+        let start = self.start_minutes()?;
+        let end = self.end_minutes()?;
+        let duration = match &self.duration {
+            Some(_) if self.is_fill() => None,
+            Some(duration) => Some(parse_duration(duration)? as f64 / 60.0),
+            None => None,
+        };
+
+        if start.is_some() && self.after.is_some() {
+            return Err(invalid!(
+                "Routine '{}' can't have both a start time and an 'after' anchor",
+                self.name
+            ));
+        }
+        if self.is_fill() && end.is_some() {
+            return Err(invalid!(
+                "Routine '{}' can't have both an end time and 'duration: fill'",
+                self.name
+            ));
+        }
+
+        match (start, end, duration) {
+            _ if self.is_fill() => Ok(()),
+            (_, None, None) => Err(invalid!(
+                "Routine '{}' needs either a duration or an end time",
+                self.name
+            )),
+            (Some(start), Some(end), Some(duration)) if minutes_between(start, end) != duration => {
+                Err(invalid!(
+                    "Routine '{}' has a start, end, and duration that don't agree",
+                    self.name
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Name of the routine.
     pub fn name(&self) -> &str {
         &self.name
     }
 
-    // Duration of the routine in minutes
-    pub fn duration(&self) -> Result<f64> {
-        let re = Regex::new(r"^(\d+(?:\.\d+)?)\s*(m|h)$")?;
-        let caps = re
-            .captures(&self.duration)
-            .ok_or_else(|| anyhow!("Invalid duration format: {}", self.duration))?;
-
-        let value: f64 = caps[1].parse()?;
-        let unit = &caps[2];
+    /// The fields that control when the routine happens, in the order they
+    /// are written to plan.yaml.
+    pub fn timing_fields(&self) -> Vec<(&'static str, Option<String>)> {
+        vec![
+            ("start", self.start.clone()),
+            ("after", self.after.clone()),
+            ("end", self.end.clone()),
+            ("duration", self.duration.clone()),
+        ]
+    }
 
-        Ok(match unit {
-            "h" => value * 60.0,
-            "m" => value,
-            _ => return Err(anyhow!("Invalid duration unit: {}", unit)),
-        })
+    /// True if the routine stretches to fill the time until the next
+    /// routine with a fixed start (i.e. `duration: fill`).
+    pub fn is_fill(&self) -> bool {
+        self.duration
+            .as_deref()
+            .is_some_and(|d| d.trim().eq_ignore_ascii_case("fill"))
     }
 
-    pub fn start_minutes(&self) -> Result<Option<u32>> {
-        let Some(ref start) = self.start else {
+    /// The routine this one is anchored after and the offset in minutes,
+    /// parsed from e.g. "Lunch" or "Lunch + 15m".  The text after the last
+    /// "+" is only taken as the offset if it's a duration, so a routine
+    /// named e.g. "Gym+Swim" can be an anchor too.
+    pub fn after_anchor(&self) -> Result<Option<(String, u32)>> {
+        let Some(after) = &self.after else {
             return Ok(None);
         };
-
-        let start = start.trim().to_lowercase();
-        let re = Regex::new(r"^(\d{1,2}):?(\d{2})?\s*(am|pm)?$")?;
-        let caps = re.captures(&start).ok_or_else(|| {
-            anyhow!(
-                "Invalid start time format: {}",
-                self.start.as_ref().unwrap()
-            )
-        })?;
-
-        let mut hour: u32 = caps[1].parse()?;
-        let minute: u32 = caps.get(2).map_or(0, |m| m.as_str().parse().unwrap_or(0));
-
-        if let Some(ampm) = caps.get(3) {
-            match ampm.as_str() {
-                "pm" if hour < 12 => hour += 12,
-                "am" if hour == 12 => hour = 0,
-                _ => {}
+        let anchor = after.rsplit_once('+').and_then(|(name, offset)| {
+            parse_duration(offset)
+                .ok()
+                .map(|seconds| (name, offset, seconds))
+        });
+        let (name, offset) = match anchor {
+            Some((_, offset, seconds)) if seconds % 60 != 0 => {
+                return Err(invalid!(
+                    "Routine '{}' has an 'after' offset of '{}', which isn't a whole number of minutes",
+                    self.name,
+                    offset.trim()
+                ));
             }
+            Some((name, _, seconds)) => (name, seconds / 60),
+            None => (after.as_str(), 0),
+        };
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(invalid!(
+                "Routine '{}' has an 'after' anchor without a routine name",
+                self.name
+            ));
         }
+        Ok(Some((name.to_string(), offset)))
+    }
 
-        Ok(Some(hour * 60 + minute))
+    /// Points an `after` anchor on the routine `from` at the routine `to`
+    /// instead, keeping the offset.  Returns true if the anchor changed.
+    pub fn rename_anchor(&mut self, from: &str, to: &str) -> bool {
+        let Ok(Some((name, _))) = self.after_anchor() else {
+            return false;
+        };
+        let Some(after) = &self.after else {
+            return false;
+        };
+        if !name.eq_ignore_ascii_case(from) {
+            return false;
+        }
+        // The name is at the start of the text, after any whitespace
+        let rest = &after.trim_start()[name.len()..];
+        self.after = Some(format!("{}{}", to, rest));
+        true
     }
 
-    pub fn pretty_duration(&self) -> Result<String> {
-        let duration = self.duration()?;
-        if duration <= 90.0 {
-            Ok(format!("{}m", duration as u32))
-        } else {
-            let hours = duration / 60.0;
-            let formatted = if hours.fract() == 0.0 {
-                format!("{}h", hours as u32)
-            } else {
-                format!("{:.1}h", hours)
-            };
-            Ok(formatted)
+    /// Duration of the routine in minutes.  Routines that only give an end
+    /// time need a start time to have a duration; see `duration_from`.
+    /// Routines that fill the available time only have a duration once the
+    /// schedule is resolved.
+    pub fn duration(&self) -> Result<f64> {
+        if self.is_fill() {
+            return Err(invalid!(
+                "Routine '{}' fills the available time; its duration depends on the schedule",
+                self.name
+            ));
+        }
+        match (&self.duration, self.start_minutes()?) {
+            (Some(duration), _) => Ok(parse_duration(duration)? as f64 / 60.0),
+            (None, Some(start)) => self.duration_from(start),
+            (None, None) => Err(invalid!(
+                "Routine '{}' has no duration or start time to compute one from",
+                self.name
+            )),
         }
     }
 
+    /// Duration of the routine in minutes when it starts at the given
+    /// time-of-day (in minutes).
+    pub fn duration_from(&self, start: u32) -> Result<f64> {
+        if self.is_fill() {
+            return self.duration();
+        }
+        if let Some(duration) = &self.duration {
+            return Ok(parse_duration(duration)? as f64 / 60.0);
+        }
+        let Some(end) = self.end_minutes()? else {
+            return Err(invalid!(
+                "Routine '{}' needs either a duration or an end time",
+                self.name
+            ));
+        };
+        Ok(minutes_between(start, end))
+    }
+
+    /// The start time in minutes after midnight, if the routine has one.
+    pub fn start_minutes(&self) -> Result<Option<u32>> {
+        match &self.start {
+            Some(start) => Ok(Some(parse_time_of_day(start)? / 60)),
+            None => Ok(None),
+        }
+    }
+
+    /// The end time in minutes after midnight, if the routine has one.
+    pub fn end_minutes(&self) -> Result<Option<u32>> {
+        match &self.end {
+            Some(end) => Ok(Some(parse_time_of_day(end)? / 60)),
+            None => Ok(None),
+        }
+    }
+
+    /// The duration of the routine when it starts at `start`, e.g. "1h30m".
+    pub fn pretty_duration(&self, start: u32) -> Result<String> {
+        Ok(format_duration(self.duration_from(start)?))
+    }
+
+    /// The activities for a day of the week: the `everyday` list, then the
+    /// `weekdays` or `weekends` list, then the day's own list or `default`.
     pub fn activities(&self, day: DayOfWeek) -> Vec<String> {
         let mut activities = Vec::new();
 
@@ -116,3 +271,75 @@ impl Routine {
         activities
     }
 }
+
+/// Minutes from start to end, where an end at or before the start is taken
+/// to be on the following day.
+fn minutes_between(start: u32, end: u32) -> f64 {
+    let end = if end <= start { end + 24 * 60 } else { end };
+    (end - start) as f64
+}
+
+/// A duration in canonical form, leaving "fill" and invalid durations as
+/// they are apart from case and whitespace.
+fn canonical_duration_text(text: &str) -> String {
+    let text = text.trim();
+    if text.eq_ignore_ascii_case("fill") {
+        return "fill".to_string();
+    }
+    match parse_duration(text) {
+        Ok(seconds) => canonical_duration(seconds),
+        Err(_) => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn anchored(after: &str) -> Routine {
+        Routine {
+            name: "Walk".to_string(),
+            start: None,
+            after: Some(after.to_string()),
+            end: None,
+            duration: Some("30m".to_string()),
+            activities: HashMap::new(),
+        }
+    }
+
+    fn anchor(after: &str) -> Option<(String, u32)> {
+        anchored(after).after_anchor().unwrap()
+    }
+
+    #[test]
+    fn canonical_anchors() {
+        let canonical = |after: &str| anchored(after).canonical().after.unwrap();
+        assert_eq!(canonical(" Lunch +15 min "), "Lunch + 15m");
+        assert_eq!(canonical("Lunch+1.5h"), "Lunch + 1h30m");
+        assert_eq!(canonical("Gym+Swim"), "Gym+Swim");
+        assert_eq!(canonical("Gym+Swim+5m"), "Gym+Swim + 5m");
+        assert_eq!(canonical(" Lunch + 90s "), "Lunch + 90s");
+    }
+
+    #[test]
+    fn after_anchor_offsets() {
+        assert_eq!(anchor("Lunch"), Some(("Lunch".to_string(), 0)));
+        assert_eq!(anchor("Lunch + 15m"), Some(("Lunch".to_string(), 15)));
+        assert_eq!(anchor("Lunch+1h30m"), Some(("Lunch".to_string(), 90)));
+        assert_eq!(anchor("Gym+Swim"), Some(("Gym+Swim".to_string(), 0)));
+        assert_eq!(anchor("Gym+Swim + 5m"), Some(("Gym+Swim".to_string(), 5)));
+        assert!(anchored("Lunch + 90s").after_anchor().is_err());
+        assert!(anchored(" + 5m").after_anchor().is_err());
+    }
+
+    #[test]
+    fn rename_anchor_keeps_the_offset() {
+        let mut routine = anchored("morning + 15m");
+        assert!(routine.rename_anchor("Morning", "Dawn"));
+        assert_eq!(routine.after.as_deref(), Some("Dawn + 15m"));
+
+        let mut routine = anchored("Lunch");
+        assert!(!routine.rename_anchor("Morning", "Dawn"));
+        assert_eq!(routine.after.as_deref(), Some("Lunch"));
+    }
+}
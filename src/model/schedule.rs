@@ -0,0 +1,751 @@
+//! Resolves the routines of a plan into concrete blocks of time for a given
+//! day, taking into account the different ways a routine can be anchored.
+//!
+//! All times are in minutes since midnight.  A day runs from the start of
+//! its first block for 24 hours, so a time earlier than the first block
+//! (e.g. "12:30 am" in a day starting at 7:00 am) is taken to be on the
+//! following calendar day and is represented as a value past 24 * 60.
+//!
+use crate::internal::*;
+
+const DAY_MINUTES: u32 = 24 * 60;
+
+/// How the start of a block was determined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Anchor {
+    /// Starts where the previous block ended
+    Flow,
+    /// Has a fixed `start` time
+    Start,
+    /// Starts relative to the end of another routine (`after`)
+    After,
+    /// Has a fixed `end` time with the start computed backward
+    End,
+}
+
+/// A routine placed at a concrete time of day.
+#[derive(Debug, Clone, Serialize)]
+pub struct ScheduledBlock {
+    /// Index of the routine in the plan's daily_routine
+    pub index: usize,
+    /// Name of the routine
+    pub name: String,
+    /// Minutes after midnight; past 24h for blocks after midnight
+    pub start: u32,
+    /// Length of the block in minutes
+    pub duration: u32,
+    /// How the start time was determined
+    pub anchor: Anchor,
+    /// True if the duration stretches to the next fixed start
+    pub fill: bool,
+    /// The routine's activities for the day
+    pub activities: Vec<String>,
+    /// Minutes the start was moved by today's shifts
+    pub shifted: i32,
+    /// Minutes the duration was changed by today's shifts
+    pub resized: i32,
+}
+
+impl ScheduledBlock {
+    /// Minutes after midnight the block ends at.
+    pub fn end(&self) -> u32 {
+        self.start + self.duration
+    }
+
+    /// True if the block was given a fixed time, so it shouldn't move when
+    /// the schedule is compressed.
+    pub fn is_fixed(&self) -> bool {
+        matches!(self.anchor, Anchor::Start | Anchor::End)
+    }
+
+    fn move_by(&mut self, minutes: i32) {
+        self.start = (self.start as i32 + minutes).max(0) as u32;
+        self.shifted += minutes;
+    }
+
+    fn resize_by(&mut self, minutes: i32) {
+        let duration = (self.duration as i32 + minutes).max(0);
+        self.resized += duration - self.duration as i32;
+        self.duration = duration as u32;
+    }
+}
+
+/// A constraint in the plan that could not be satisfied.
+#[derive(Debug, Clone, Serialize)]
+pub struct Conflict {
+    /// Index of the routine in the plan's daily_routine
+    pub index: usize,
+    /// What couldn't be satisfied
+    pub message: String,
+}
+
+/// The blocks of time of a plan on a given day.
+#[derive(Debug, Clone, Serialize)]
+pub struct Schedule {
+    /// The day the schedule is for
+    pub day: DayOfWeek,
+    /// The routines in the order they happen
+    pub blocks: Vec<ScheduledBlock>,
+    /// Routines that couldn't be placed as the plan asks
+    pub conflicts: Vec<Conflict>,
+}
+
+impl Schedule {
+    /// Places each routine of the plan for the given day, following `after`
+    /// anchors, backward `end` times, and `fill` durations.  Constraints
+    /// that can't be met are listed in `conflicts` rather than failing.
+    pub fn resolve(plan: &Plan, day: DayOfWeek) -> Result<Schedule> {
+        let routines = &plan.daily_routine;
+        let mut schedule = Schedule {
+            day: day.clone(),
+            blocks: Vec::new(),
+            conflicts: Vec::new(),
+        };
+        let Some(first) = routines.first() else {
+            return Ok(schedule);
+        };
+
+        plan.validate()?;
+
+        let day_start = static_start(first, 0)?.unwrap_or(0);
+        let mut cursor = TimeCursor::new(day_start);
+
+        for (index, routine) in routines.iter().enumerate() {
+            let (start, anchor) = schedule.resolve_start(routines, index, day_start, &cursor)?;
+            cursor.set(start);
+
+            let duration = if routine.is_fill() {
+                schedule.resolve_fill(routines, index, start, day_start)?
+            } else {
+                routine.duration_from(start % DAY_MINUTES)? as u32
+            };
+
+            schedule.blocks.push(ScheduledBlock {
+                index,
+                name: routine.name.clone(),
+                start,
+                duration,
+                anchor,
+                fill: routine.is_fill(),
+                activities: routine.activities(day.clone()),
+                shifted: 0,
+                resized: 0,
+            });
+            cursor.add(duration);
+        }
+
+        Ok(schedule)
+    }
+
+    /// Index into `blocks` of the block in progress at the given
+    /// time-of-day, if any.  Where blocks overlap, the later one wins.
+    pub fn index_at(&self, minutes: u32) -> Option<usize> {
+        [minutes, minutes + DAY_MINUTES].iter().find_map(|&t| {
+            self.blocks
+                .iter()
+                .rposition(|b| b.start <= t && t < b.end())
+        })
+    }
+
+    /// Minutes between the end of the previous block and the start of the
+    /// given one: positive for unplanned time, negative for an overlap.
+    pub fn gap_before(&self, index: usize) -> i32 {
+        match index {
+            0 => 0,
+            i => self.blocks[i].start as i32 - self.blocks[i - 1].end() as i32,
+        }
+    }
+
+    /// Minutes of unplanned time after the last block, until the start of
+    /// the next day.
+    pub fn gap_after_last(&self) -> u32 {
+        match (self.blocks.first(), self.blocks.last()) {
+            (Some(first), Some(last)) => (first.start + DAY_MINUTES).saturating_sub(last.end()),
+            _ => DAY_MINUTES,
+        }
+    }
+
+    /// Minutes of the day covered by at least one block.  Overlapping time
+    /// is only counted once.
+    pub fn planned_minutes(&self) -> u32 {
+        let Some(first) = self.blocks.first() else {
+            return 0;
+        };
+        let day_end = first.start + DAY_MINUTES;
+
+        let mut spans: Vec<(u32, u32)> = self
+            .blocks
+            .iter()
+            .map(|b| (b.start.min(day_end), b.end().min(day_end)))
+            .collect();
+        spans.sort();
+
+        let mut total = 0;
+        let mut covered_to = 0;
+        for (start, end) in spans {
+            let start = start.max(covered_to);
+            if end > start {
+                total += end - start;
+                covered_to = end;
+            }
+        }
+        total
+    }
+
+    /// Everything in the schedule that doesn't work out: the conflicts, and
+    /// routines that start before the previous one ends.
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems: Vec<String> = self.conflicts.iter().map(|c| c.message.clone()).collect();
+        for i in 1..self.blocks.len() {
+            let gap = self.gap_before(i);
+            if gap < 0 {
+                problems.push(format!(
+                    "'{}' overlaps '{}' by {}",
+                    self.blocks[i].name,
+                    self.blocks[i - 1].name,
+                    format_duration(-gap as f64)
+                ));
+            }
+        }
+        problems
+    }
+
+    /// Minutes of the day not covered by any block.
+    pub fn unplanned_minutes(&self) -> u32 {
+        DAY_MINUTES - self.planned_minutes()
+    }
+
+    /// Moves the block the shift starts from, and every block after it, by
+    /// the shift amount.  The block before it is stretched (or shortened) to
+    /// match, as that's usually the one that ran over.
+    ///
+    /// When compressing, flexible blocks are shortened so that the next
+    /// block with a fixed time keeps its place, as far as they're able to.
+    pub fn apply_shift(&mut self, shift: &Shift) {
+        let from = self
+            .blocks
+            .iter()
+            .position(|b| b.index == shift.index && b.name == shift.name)
+            .or_else(|| {
+                self.blocks
+                    .iter()
+                    .position(|b| b.name.eq_ignore_ascii_case(&shift.name))
+            });
+        let Some(from) = from else {
+            return;
+        };
+
+        if from > 0 {
+            self.blocks[from - 1].resize_by(shift.minutes);
+        }
+
+        let mut delay = shift.minutes;
+        let mut segment = from;
+        for i in from..self.blocks.len() {
+            if shift.compress && delay > 0 && i > from && self.blocks[i].is_fixed() {
+                delay -= self.compress(segment..i, delay as u32) as i32;
+                segment = i;
+            }
+            self.blocks[i].move_by(delay);
+        }
+    }
+
+    /// Shortens the blocks in the range, latest first, to absorb up to
+    /// `delay` minutes.  Returns the number of minutes absorbed.
+    fn compress(&mut self, range: std::ops::Range<usize>, delay: u32) -> u32 {
+        let mut remaining = delay;
+        for i in range.clone().rev() {
+            let cut = remaining.min(self.blocks[i].duration);
+            if cut == 0 {
+                continue;
+            }
+            self.blocks[i].resize_by(-(cut as i32));
+            for block in &mut self.blocks[i + 1..range.end] {
+                block.move_by(-(cut as i32));
+            }
+            remaining -= cut;
+            if remaining == 0 {
+                break;
+            }
+        }
+        delay - remaining
+    }
+
+    //------------------------------------------------------------------------//
+    // Resolution helpers
+    //------------------------------------------------------------------------//
+
+    fn resolve_start(
+        &mut self,
+        routines: &[Routine],
+        index: usize,
+        day_start: u32,
+        cursor: &TimeCursor,
+    ) -> Result<(u32, Anchor)> {
+        let routine = &routines[index];
+        let anchor = match routine.after_anchor() {
+            Ok(anchor) => anchor,
+            Err(e) => {
+                self.conflicts.push(Conflict {
+                    index,
+                    message: e.to_string(),
+                });
+                return Ok((cursor.cursor, Anchor::Flow));
+            }
+        };
+        if let Some((name, offset)) = anchor {
+            let target = self
+                .blocks
+                .iter()
+                .rev()
+                .find(|b| b.name.eq_ignore_ascii_case(&name));
+            return Ok(match target {
+                Some(target) => (target.end() + offset, Anchor::After),
+                None => {
+                    let exists = routines.iter().any(|r| r.name.eq_ignore_ascii_case(&name));
+                    let message = if exists {
+                        format!(
+                            "'{}' is anchored after '{}', which comes later in the day",
+                            routine.name, name
+                        )
+                    } else {
+                        format!(
+                            "'{}' is anchored after '{}', which doesn't exist",
+                            routine.name, name
+                        )
+                    };
+                    self.conflicts.push(Conflict { index, message });
+                    (cursor.cursor, Anchor::Flow)
+                }
+            });
+        }
+
+        Ok(match static_start(routine, day_start)? {
+            Some(start) if routine.start.is_some() => (start, Anchor::Start),
+            Some(start) => (start, Anchor::End),
+            None => (cursor.cursor, Anchor::Flow),
+        })
+    }
+
+    /// The duration of a fill block: the time until the next routine with a
+    /// fixed start, less the time needed by the routines in between.  With
+    /// no later fixed start, the block fills the rest of the day.
+    fn resolve_fill(
+        &mut self,
+        routines: &[Routine],
+        index: usize,
+        start: u32,
+        day_start: u32,
+    ) -> Result<u32> {
+        let mut between = 0;
+        let mut target = day_start + DAY_MINUTES;
+
+        for later in &routines[index + 1..] {
+            if let Some(fixed) = static_start(later, day_start)? {
+                target = fixed;
+                break;
+            }
+            if later.is_fill() {
+                continue;
+            }
+            if later.duration.is_none() {
+                self.conflicts.push(Conflict {
+                    index,
+                    message: format!(
+                        "'{}' can't fill up to '{}', which only has an end time",
+                        routines[index].name, later.name
+                    ),
+                });
+                return Ok(0);
+            }
+            // An anchor that can't be parsed is reported when its routine
+            // is resolved
+            let offset = later
+                .after_anchor()
+                .ok()
+                .flatten()
+                .map_or(0, |(_, offset)| offset);
+            between += offset + later.duration()? as u32;
+        }
+
+        match target.checked_sub(start + between) {
+            Some(duration) if duration > 0 => Ok(duration),
+            _ => {
+                self.conflicts.push(Conflict {
+                    index,
+                    message: format!("'{}' has no time left to fill", routines[index].name),
+                });
+                Ok(0)
+            }
+        }
+    }
+}
+
+/// The start of a routine if it can be known without resolving the rest of
+/// the schedule: either a fixed start, or an end time and duration.
+fn static_start(routine: &Routine, day_start: u32) -> Result<Option<u32>> {
+    if let Some(start) = routine.start_minutes()? {
+        return Ok(Some(in_day(start, day_start)));
+    }
+    if routine.after.is_some() || routine.is_fill() || routine.duration.is_none() {
+        return Ok(None);
+    }
+    let Some(end) = routine.end_minutes()? else {
+        return Ok(None);
+    };
+    let end = in_day(end, day_start);
+    let duration = routine.duration()? as u32;
+    Ok(Some(match end.checked_sub(duration) {
+        Some(start) => start,
+        None => end + DAY_MINUTES - duration,
+    }))
+}
+
+/// Times before the start of the day belong to the following calendar day.
+fn in_day(minutes: u32, day_start: u32) -> u32 {
+    if minutes < day_start {
+        minutes + DAY_MINUTES
+    } else {
+        minutes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve(yaml: &str) -> Schedule {
+        let plan = Plan::from_yaml(yaml).unwrap();
+        Schedule::resolve(&plan, DayOfWeek::Mon).unwrap()
+    }
+
+    /// (name, start, duration) of each block, with times as "H:MM".
+    fn blocks(schedule: &Schedule) -> Vec<(String, String, u32)> {
+        schedule
+            .blocks
+            .iter()
+            .map(|b| {
+                let start = format!("{}:{:02}", b.start / 60, b.start % 60);
+                (b.name.clone(), start, b.duration)
+            })
+            .collect()
+    }
+
+    fn block(name: &str, start: &str, duration: u32) -> (String, String, u32) {
+        (name.to_string(), start.to_string(), duration)
+    }
+
+    fn messages(schedule: &Schedule) -> Vec<&str> {
+        schedule
+            .conflicts
+            .iter()
+            .map(|c| c.message.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn routines_flow_from_a_fixed_start() {
+        let schedule = resolve(
+            "daily_routine:
+  - name: Wake
+    start: 7am
+    duration: 30m
+  - name: Work
+    duration: 3h
+  - name: Lunch
+    start: noon
+    end: 1pm
+",
+        );
+        assert_eq!(
+            blocks(&schedule),
+            vec![
+                block("Wake", "7:00", 30),
+                block("Work", "7:30", 180),
+                block("Lunch", "12:00", 60),
+            ]
+        );
+        assert_eq!(schedule.blocks[1].anchor, Anchor::Flow);
+        assert_eq!(schedule.gap_before(2), 90);
+        assert!(schedule.conflicts.is_empty());
+    }
+
+    #[test]
+    fn after_anchors_follow_the_named_routine() {
+        let schedule = resolve(
+            "daily_routine:
+  - name: Lunch
+    start: noon
+    duration: 1h
+  - name: Walk
+    after: lunch + 15m
+    duration: 30m
+  - name: Gym+Swim
+    duration: 1h
+  - name: Shower
+    after: Gym+Swim
+    duration: 15m
+",
+        );
+        assert_eq!(
+            blocks(&schedule),
+            vec![
+                block("Lunch", "12:00", 60),
+                block("Walk", "13:15", 30),
+                block("Gym+Swim", "13:45", 60),
+                block("Shower", "14:45", 15),
+            ]
+        );
+        assert_eq!(schedule.blocks[1].anchor, Anchor::After);
+        assert_eq!(schedule.blocks[3].anchor, Anchor::After);
+        assert!(schedule.conflicts.is_empty());
+    }
+
+    #[test]
+    fn end_and_duration_place_a_routine_backward() {
+        let schedule = resolve(
+            "daily_routine:
+  - name: Wake
+    start: 7am
+    duration: 30m
+  - name: Lunch
+    end: 1pm
+    duration: 45m
+",
+        );
+        assert_eq!(
+            blocks(&schedule),
+            vec![block("Wake", "7:00", 30), block("Lunch", "12:15", 45)]
+        );
+        assert_eq!(schedule.blocks[1].anchor, Anchor::End);
+    }
+
+    #[test]
+    fn fill_stretches_to_the_next_fixed_start() {
+        let schedule = resolve(
+            "daily_routine:
+  - name: Wake
+    start: 7am
+    duration: 30m
+  - name: Deep work
+    duration: fill
+  - name: Break
+    duration: 15m
+  - name: Lunch
+    start: noon
+    duration: 1h
+  - name: Evening
+    duration: fill
+",
+        );
+        assert_eq!(
+            blocks(&schedule),
+            vec![
+                block("Wake", "7:00", 30),
+                block("Deep work", "7:30", 255),
+                block("Break", "11:45", 15),
+                block("Lunch", "12:00", 60),
+                // The rest of the day, until the next day starts at 7am
+                block("Evening", "13:00", 18 * 60),
+            ]
+        );
+        assert!(schedule.blocks[1].fill);
+        assert!(schedule.conflicts.is_empty());
+    }
+
+    #[test]
+    fn missing_and_later_anchors_are_conflicts() {
+        let schedule = resolve(
+            "daily_routine:
+  - name: Wake
+    start: 7am
+    duration: 30m
+  - name: Walk
+    after: Nope
+    duration: 30m
+  - name: Coffee
+    after: Lunch
+    duration: 15m
+  - name: Lunch
+    duration: 1h
+",
+        );
+        assert_eq!(
+            messages(&schedule),
+            vec![
+                "'Walk' is anchored after 'Nope', which doesn't exist",
+                "'Coffee' is anchored after 'Lunch', which comes later in the day",
+            ]
+        );
+        // The routines still get a place in the schedule
+        assert_eq!(schedule.blocks[1].anchor, Anchor::Flow);
+        assert_eq!(schedule.blocks[1].start, 7 * 60 + 30);
+    }
+
+    #[test]
+    fn invalid_anchor_offsets_are_conflicts() {
+        let schedule = resolve(
+            "daily_routine:
+  - name: Wake
+    start: 7am
+    duration: 30m
+  - name: Walk
+    after: Wake + 30s
+    duration: 30m
+  - name: Run
+    after: ' + 5m'
+    duration: 30m
+",
+        );
+        assert_eq!(schedule.blocks.len(), 3);
+        assert_eq!(schedule.conflicts.len(), 2);
+        assert!(messages(&schedule)[0].contains("isn't a whole number of minutes"));
+        assert!(messages(&schedule)[1].contains("without a routine name"));
+    }
+
+    #[test]
+    fn fill_without_room_is_a_conflict() {
+        let schedule = resolve(
+            "daily_routine:
+  - name: Work
+    start: 9am
+    duration: 3h
+  - name: Catch up
+    duration: fill
+  - name: Lunch
+    start: noon
+    duration: 1h
+  - name: Admin
+    duration: fill
+  - name: Wind down
+    end: 5pm
+",
+        );
+        assert_eq!(
+            messages(&schedule),
+            vec![
+                "'Catch up' has no time left to fill",
+                "'Admin' can't fill up to 'Wind down', which only has an end time",
+            ]
+        );
+        assert_eq!(schedule.blocks[1].duration, 0);
+    }
+
+    const SHIFT_PLAN: &str = "daily_routine:
+  - name: Wake
+    start: 7am
+    duration: 30m
+  - name: Work
+    duration: 3h
+  - name: Break
+    duration: 30m
+  - name: Lunch
+    start: noon
+    duration: 1h
+  - name: Walk
+    duration: 30m
+";
+
+    fn shifted(yaml: &str, name: &str, minutes: i32, compress: bool) -> Schedule {
+        let mut schedule = resolve(yaml);
+        let index = schedule.blocks.iter().position(|b| b.name == name).unwrap();
+        schedule.apply_shift(&Shift {
+            index: schedule.blocks[index].index,
+            name: name.to_string(),
+            minutes,
+            compress,
+        });
+        schedule
+    }
+
+    #[test]
+    fn shifting_later_moves_the_rest_of_the_day() {
+        let schedule = shifted(SHIFT_PLAN, "Work", 20, false);
+        assert_eq!(
+            blocks(&schedule),
+            vec![
+                block("Wake", "7:00", 50),
+                block("Work", "7:50", 180),
+                block("Break", "10:50", 30),
+                block("Lunch", "12:20", 60),
+                block("Walk", "13:20", 30),
+            ]
+        );
+        assert_eq!(schedule.blocks[0].resized, 20);
+        assert_eq!(schedule.blocks[0].shifted, 0);
+        assert!(schedule.blocks[1..].iter().all(|b| b.shifted == 20));
+    }
+
+    #[test]
+    fn shifting_earlier_shortens_the_block_before() {
+        let schedule = shifted(SHIFT_PLAN, "Work", -10, false);
+        assert_eq!(
+            blocks(&schedule),
+            vec![
+                block("Wake", "7:00", 20),
+                block("Work", "7:20", 180),
+                block("Break", "10:20", 30),
+                block("Lunch", "11:50", 60),
+                block("Walk", "12:50", 30),
+            ]
+        );
+        assert_eq!(schedule.blocks[0].resized, -10);
+    }
+
+    #[test]
+    fn shifting_can_push_past_midnight() {
+        let schedule = shifted(
+            "daily_routine:
+  - name: Evening
+    start: 11pm
+    duration: 30m
+  - name: Read
+    duration: 30m
+",
+            "Read",
+            45,
+            false,
+        );
+        assert_eq!(
+            blocks(&schedule),
+            vec![block("Evening", "23:00", 75), block("Read", "24:15", 30)]
+        );
+        assert_eq!(TimeCursor::new(schedule.blocks[1].start).pretty(), "00:15");
+        assert_eq!(TimeCursor::new(schedule.blocks[1].end()).pretty(), "00:45");
+    }
+
+    #[test]
+    fn compressing_keeps_fixed_blocks_in_place() {
+        let schedule = shifted(SHIFT_PLAN, "Work", 45, true);
+        assert_eq!(
+            blocks(&schedule),
+            vec![
+                block("Wake", "7:00", 75),
+                block("Work", "8:15", 165),
+                block("Break", "11:00", 0),
+                block("Lunch", "12:00", 60),
+                block("Walk", "13:00", 30),
+            ]
+        );
+        assert_eq!(schedule.blocks[1].resized, -15);
+        assert_eq!(schedule.blocks[2].resized, -30);
+        assert_eq!(schedule.blocks[3].shifted, 0);
+    }
+
+    #[test]
+    fn compressing_more_than_the_flexible_time_moves_fixed_blocks() {
+        let schedule = shifted(SHIFT_PLAN, "Break", 60, true);
+        // Break can only absorb 30 of the 60 minutes
+        assert_eq!(
+            blocks(&schedule),
+            vec![
+                block("Wake", "7:00", 30),
+                block("Work", "7:30", 240),
+                block("Break", "11:30", 0),
+                block("Lunch", "12:30", 60),
+                block("Walk", "13:30", 30),
+            ]
+        );
+    }
+}
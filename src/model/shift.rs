@@ -0,0 +1,97 @@
+//! Today-only adjustments to the schedule for when the day doesn't go to
+//! plan, e.g. a meeting running over.  Shifts are kept outside the data
+//! repository and only apply on the date they were made.
+//!
+use crate::internal::*;
+use chrono::NaiveDate;
+
+/// A change to today's schedule from one routine onward.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Shift {
+    /// Position in the daily routine of the first routine to move
+    pub index: usize,
+    /// Name of that routine, in case the plan is edited during the day
+    pub name: String,
+    /// Number of minutes to move by (negative to move earlier)
+    pub minutes: i32,
+    /// Shrink flexible routines so that fixed-time routines stay put
+    #[serde(default)]
+    pub compress: bool,
+}
+
+/// The shifts made on one day, in the order they were made.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DayShifts {
+    /// The day the shifts apply to
+    pub date: NaiveDate,
+    /// The shifts, applied in order
+    #[serde(default)]
+    pub shifts: Vec<Shift>,
+}
+
+impl DayShifts {
+    /// No shifts for the given day.
+    pub fn new(date: NaiveDate) -> Self {
+        Self {
+            date,
+            shifts: Vec::new(),
+        }
+    }
+}
+
+/// Parses a signed shift amount such as "+20m", "-10 min", or "1h".
+pub fn parse_shift_amount(text: &str) -> Result<i32> {
+    let text = text.trim();
+    let (sign, rest) = match text.strip_prefix('-') {
+        Some(rest) => (-1, rest),
+        None => (1, text.strip_prefix('+').unwrap_or(text)),
+    };
+    let minutes = (parse_duration(rest)? as f64 / 60.0).round() as i32;
+    if minutes == 0 {
+        return Err(invalid!("Shift amount '{}' is less than a minute", text));
+    }
+    Ok(sign * minutes)
+}
+
+/// Formats a signed number of minutes, e.g. "+20m" or "-1h30m", in a form
+/// `parse_shift_amount` reads back exactly.
+pub fn format_shift_amount(minutes: i32) -> String {
+    let sign = if minutes < 0 { "-" } else { "+" };
+    format!(
+        "{}{}",
+        sign,
+        canonical_duration(minutes.unsigned_abs() * 60)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shift_amounts() {
+        assert_eq!(parse_shift_amount("+20m").unwrap(), 20);
+        assert_eq!(parse_shift_amount("20m").unwrap(), 20);
+        assert_eq!(parse_shift_amount(" -10 min ").unwrap(), -10);
+        assert_eq!(parse_shift_amount("1h30m").unwrap(), 90);
+        assert_eq!(parse_shift_amount("-1.5h").unwrap(), -90);
+        assert_eq!(parse_shift_amount("90s").unwrap(), 2);
+    }
+
+    #[test]
+    fn invalid_shift_amounts() {
+        for text in ["", "+", "-", "soon", "20", "+-20m", "10s", "0m"] {
+            assert!(parse_shift_amount(text).is_err(), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn shift_amounts_round_trip() {
+        for minutes in [1, -1, 20, -20, 45, 90, -90, 100, 150, 24 * 60] {
+            let text = format_shift_amount(minutes);
+            assert_eq!(parse_shift_amount(&text).unwrap(), minutes, "{}", text);
+        }
+        assert_eq!(format_shift_amount(20), "+20m");
+        assert_eq!(format_shift_amount(-90), "-1h30m");
+    }
+}
@@ -0,0 +1,661 @@
+//! Parsers for the time-of-day and duration strings used in plan.yaml.
+//!
+//! Times of day accept 12 and 24 hour clocks with optional minutes and
+//! seconds ("7", "7am", "7:30 pm", "930am", "13:00", "13:00:30"), as well as
+//! "noon" and "midnight".
+//!
+//! Durations are one or more number-unit pairs ("30m", "1.5h", "1h30m",
+//! "90 min", "2 hours 15 minutes", "45s") or a clock-style "H:MM[:SS]".
+//!
+use std::fmt;
+
+/// Describes exactly what was wrong with a time or duration string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TimeParseError {
+    /// The string was empty
+    Empty {
+        /// What was being parsed, e.g. "time of day"
+        what: &'static str,
+    },
+    /// A token that isn't valid at its position
+    UnexpectedToken {
+        /// What was being parsed, e.g. "time of day"
+        what: &'static str,
+        /// The whole string
+        input: String,
+        /// The invalid token
+        token: String,
+        /// What was expected instead, e.g. "a number"
+        expected: &'static str,
+    },
+    /// A duration number without a unit, e.g. the "30" in "1h30"
+    MissingUnit {
+        /// The whole string
+        input: String,
+        /// The number without a unit
+        number: String,
+    },
+    /// A duration unit that isn't recognized, e.g. "1 fortnight"
+    UnknownUnit {
+        /// The whole string
+        input: String,
+        /// The unrecognized unit
+        unit: String,
+    },
+    /// The same duration unit given twice, e.g. "1h2h"
+    DuplicateUnit {
+        /// The whole string
+        input: String,
+        /// The repeated unit
+        unit: String,
+    },
+    /// A number outside the range of its field, e.g. "25:00"
+    OutOfRange {
+        /// What was being parsed, e.g. "time of day"
+        what: &'static str,
+        /// The whole string
+        input: String,
+        /// The out-of-range number
+        token: String,
+        /// The field the number is for, e.g. "hour"
+        field: &'static str,
+        /// The largest value the field allows
+        max: u32,
+    },
+    /// A duration too long to represent, e.g. "5000000:00"
+    TooLong {
+        /// The whole string
+        input: String,
+    },
+}
+
+impl fmt::Display for TimeParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimeParseError::Empty { what } => write!(f, "{} is empty", what),
+            TimeParseError::UnexpectedToken {
+                what,
+                input,
+                token,
+                expected,
+            } => write!(
+                f,
+                "invalid {} '{}': unexpected '{}' (expected {})",
+                what, input, token, expected
+            ),
+            TimeParseError::MissingUnit { input, number } => write!(
+                f,
+                "invalid duration '{}': '{}' is missing a unit (h, m, or s)",
+                input, number
+            ),
+            TimeParseError::UnknownUnit { input, unit } => write!(
+                f,
+                "invalid duration '{}': unknown unit '{}' (expected h, m, or s)",
+                input, unit
+            ),
+            TimeParseError::DuplicateUnit { input, unit } => write!(
+                f,
+                "invalid duration '{}': unit '{}' is given more than once",
+                input, unit
+            ),
+            TimeParseError::OutOfRange {
+                what,
+                input,
+                token,
+                field,
+                max,
+            } => write!(
+                f,
+                "invalid {} '{}': {} '{}' is out of range (0-{})",
+                what, input, field, token, max
+            ),
+            TimeParseError::TooLong { input } => {
+                write!(f, "invalid duration '{}': it is too long", input)
+            }
+        }
+    }
+}
+
+impl std::error::Error for TimeParseError {}
+
+//===========================================================================//
+// Tokenizer
+//===========================================================================//
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(String),
+    Word(String),
+    Colon,
+    Other(char),
+}
+
+impl Token {
+    fn text(&self) -> String {
+        match self {
+            Token::Number(s) | Token::Word(s) => s.clone(),
+            Token::Colon => ":".to_string(),
+            Token::Other(c) => c.to_string(),
+        }
+    }
+}
+
+fn tokenize(input: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_digit() || c == '.' {
+            let mut number = String::new();
+            while let Some(&c) = chars.peek().filter(|c| c.is_ascii_digit() || **c == '.') {
+                number.push(c);
+                chars.next();
+            }
+            tokens.push(Token::Number(number));
+        } else if c.is_alphabetic() {
+            let mut word = String::new();
+            while let Some(&c) = chars.peek().filter(|c| c.is_alphabetic() || **c == '.') {
+                word.push(c);
+                chars.next();
+            }
+            tokens.push(Token::Word(word.to_lowercase()));
+        } else if c == ':' {
+            tokens.push(Token::Colon);
+            chars.next();
+        } else {
+            tokens.push(Token::Other(c));
+            chars.next();
+        }
+    }
+    tokens
+}
+
+//===========================================================================//
+// Durations
+//===========================================================================//
+
+/// Parses a duration, returning the number of seconds.
+pub fn parse_duration(input: &str) -> Result<u32, TimeParseError> {
+    let input = input.trim();
+    let tokens = tokenize(input);
+    if tokens.is_empty() {
+        return Err(TimeParseError::Empty { what: "duration" });
+    }
+
+    if tokens.contains(&Token::Colon) {
+        return parse_clock(input, &tokens, "duration", u32::MAX);
+    }
+
+    let mut total = 0.0;
+    let mut seen: Vec<u32> = Vec::new();
+    let mut iter = tokens.iter();
+
+    while let Some(token) = iter.next() {
+        let Token::Number(number) = token else {
+            return Err(TimeParseError::UnexpectedToken {
+                what: "duration",
+                input: input.to_string(),
+                token: token.text(),
+                expected: "a number",
+            });
+        };
+        let value: f64 = number
+            .parse()
+            .map_err(|_| TimeParseError::UnexpectedToken {
+                what: "duration",
+                input: input.to_string(),
+                token: number.clone(),
+                expected: "a number",
+            })?;
+
+        let unit = match iter.next() {
+            Some(Token::Word(unit)) => unit,
+            Some(other) => {
+                return Err(TimeParseError::UnexpectedToken {
+                    what: "duration",
+                    input: input.to_string(),
+                    token: other.text(),
+                    expected: "a unit (h, m, or s)",
+                })
+            }
+            None => {
+                return Err(TimeParseError::MissingUnit {
+                    input: input.to_string(),
+                    number: number.clone(),
+                })
+            }
+        };
+
+        let scale = match unit.as_str() {
+            "h" | "hr" | "hrs" | "hour" | "hours" => 3600,
+            "m" | "min" | "mins" | "minute" | "minutes" => 60,
+            "s" | "sec" | "secs" | "second" | "seconds" => 1,
+            _ => {
+                return Err(TimeParseError::UnknownUnit {
+                    input: input.to_string(),
+                    unit: unit.clone(),
+                })
+            }
+        };
+        if seen.contains(&scale) {
+            return Err(TimeParseError::DuplicateUnit {
+                input: input.to_string(),
+                unit: unit.clone(),
+            });
+        }
+        seen.push(scale);
+        total += value * scale as f64;
+    }
+
+    let total = total.round();
+    if total > u32::MAX as f64 {
+        return Err(TimeParseError::TooLong {
+            input: input.to_string(),
+        });
+    }
+    Ok(total as u32)
+}
+
+/// Formats a number of minutes as a short duration, e.g. "45m" or "2.5h".
+pub fn format_duration(minutes: f64) -> String {
+    if minutes <= 90.0 {
+        format!("{}m", minutes as u32)
+    } else {
+        let hours = minutes / 60.0;
+        if hours.fract() == 0.0 {
+            format!("{}h", hours as u32)
+        } else {
+            format!("{:.1}h", hours)
+        }
+    }
+}
+
+/// Formats a number of seconds as a duration in the canonical form written
+/// by `guidebook-plan fmt`, e.g. "1h30m", "45m" or "1m30s".
+pub fn canonical_duration(seconds: u32) -> String {
+    let parts = [
+        (seconds / 3600, "h"),
+        (seconds / 60 % 60, "m"),
+        (seconds % 60, "s"),
+    ];
+    let text: String = parts
+        .iter()
+        .filter(|(value, _)| *value > 0)
+        .map(|(value, unit)| format!("{}{}", value, unit))
+        .collect();
+    if text.is_empty() {
+        "0m".to_string()
+    } else {
+        text
+    }
+}
+
+//===========================================================================//
+// Times of day
+//===========================================================================//
+
+/// Parses a time of day, returning the number of seconds since midnight.
+/// "24:00" is accepted as the end of the day.
+pub fn parse_time_of_day(input: &str) -> Result<u32, TimeParseError> {
+    let input = input.trim();
+    let mut tokens = tokenize(input);
+    if tokens.is_empty() {
+        return Err(TimeParseError::Empty { what: "time" });
+    }
+
+    match tokens.as_slice() {
+        [Token::Word(word)] if word == "noon" => return Ok(12 * 3600),
+        [Token::Word(word)] if word == "midnight" => return Ok(0),
+        _ => {}
+    }
+
+    // A trailing am/pm marker switches to the 12 hour clock
+    let meridiem = match tokens.last() {
+        Some(Token::Word(word)) => match word.as_str() {
+            "am" | "a.m." | "a" => Some(false),
+            "pm" | "p.m." | "p" => Some(true),
+            _ => {
+                return Err(TimeParseError::UnexpectedToken {
+                    what: "time",
+                    input: input.to_string(),
+                    token: word.clone(),
+                    expected: "am or pm",
+                })
+            }
+        },
+        _ => None,
+    };
+    if meridiem.is_some() {
+        tokens.pop();
+    }
+
+    // A compact "930" or "1330" is read as H:MM / HH:MM
+    if let [Token::Number(number)] = tokens.as_slice() {
+        if number.len() > 2 && number.chars().all(|c| c.is_ascii_digit()) {
+            let (hours, minutes) = number.split_at(number.len() - 2);
+            tokens = vec![
+                Token::Number(hours.to_string()),
+                Token::Colon,
+                Token::Number(minutes.to_string()),
+            ];
+        }
+    }
+
+    let max_hour = if meridiem.is_some() { 12 } else { 24 };
+    let seconds = parse_clock(input, &tokens, "time", max_hour)?;
+    let hour = seconds / 3600;
+
+    if meridiem.is_some() && hour == 0 {
+        return Err(TimeParseError::OutOfRange {
+            what: "time",
+            input: input.to_string(),
+            token: "0".to_string(),
+            field: "hour",
+            max: 12,
+        });
+    }
+    if hour == 24 && seconds > 24 * 3600 {
+        return Err(TimeParseError::OutOfRange {
+            what: "time",
+            input: input.to_string(),
+            token: input.to_string(),
+            field: "time",
+            max: 24,
+        });
+    }
+
+    Ok(match meridiem {
+        Some(true) if hour < 12 => seconds + 12 * 3600,
+        Some(false) if hour == 12 => seconds - 12 * 3600,
+        _ => seconds,
+    })
+}
+
+/// Formats a number of minutes since midnight, e.g. "7:30 am".
+pub fn format_time_of_day(minutes: u32) -> String {
+    let minutes = minutes % (24 * 60);
+    let hours = minutes / 60;
+    let ampm = if hours >= 12 { "pm" } else { "am" };
+    let hours = match hours % 12 {
+        0 => 12,
+        h => h,
+    };
+    format!("{}:{:02} {}", hours, minutes % 60, ampm)
+}
+
+//===========================================================================//
+// Shared helpers
+//===========================================================================//
+
+/// Parses "H[:MM[:SS]]" tokens into a number of seconds.
+fn parse_clock(
+    input: &str,
+    tokens: &[Token],
+    what: &'static str,
+    max_hour: u32,
+) -> Result<u32, TimeParseError> {
+    let unexpected = |token: &Token, expected: &'static str| TimeParseError::UnexpectedToken {
+        what,
+        input: input.to_string(),
+        token: token.text(),
+        expected,
+    };
+
+    let mut parts: Vec<&String> = Vec::new();
+    for (i, token) in tokens.iter().enumerate() {
+        match (i % 2, token) {
+            (0, Token::Number(number)) => parts.push(number),
+            (0, token) => return Err(unexpected(token, "a number")),
+            (_, Token::Colon) => {}
+            (_, token) => return Err(unexpected(token, "':'")),
+        }
+    }
+    if tokens.len().is_multiple_of(2) || parts.len() > 3 {
+        let last = tokens.last().unwrap();
+        return Err(unexpected(last, "H, H:MM, or H:MM:SS"));
+    }
+
+    let fields = [("hour", max_hour), ("minute", 59), ("second", 59)];
+    let mut seconds: u32 = 0;
+    for (i, part) in parts.iter().enumerate() {
+        let (field, max) = fields[i];
+        let valid_width = i == 0 || part.len() == 2;
+        let value: u32 = match part.parse() {
+            Ok(value) if valid_width => value,
+            _ => {
+                return Err(TimeParseError::UnexpectedToken {
+                    what,
+                    input: input.to_string(),
+                    token: part.to_string(),
+                    expected: if i == 0 {
+                        "a whole number of hours"
+                    } else {
+                        "two digits"
+                    },
+                })
+            }
+        };
+        if value > max {
+            return Err(TimeParseError::OutOfRange {
+                what,
+                input: input.to_string(),
+                token: part.to_string(),
+                field,
+                max,
+            });
+        }
+        // Durations have no limit on the hours, so they can overflow
+        seconds = value
+            .checked_mul([3600, 60, 1][i])
+            .and_then(|value| seconds.checked_add(value))
+            .ok_or_else(|| TimeParseError::TooLong {
+                input: input.to_string(),
+            })?;
+    }
+    Ok(seconds)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Durations the parser accepts, in each of the forms of the grammar.
+    /// The schema's pattern is checked against these too.
+    pub(crate) const DURATIONS: &[&str] = &[
+        "30m",
+        "1h30m",
+        "1h 30m",
+        "90min",
+        "90 minutes",
+        "1.5h",
+        ".5h",
+        "2 hours 15 minutes",
+        "45s",
+        "1H30M",
+        "  2h  ",
+        "1:30",
+        "0:45",
+        "1:00:30",
+        "36:00",
+        "1193046h",
+    ];
+
+    /// Times of day the parser accepts, in each of the forms of the grammar.
+    pub(crate) const TIMES: &[&str] = &[
+        "7am", "7 AM", "7:00 am", "7:30 pm", "930am", "1230pm", "7 p.m.", "7p", "12am", "12:30 am",
+        "7", "13:00", "1330", "13:00:30", "0:00", "24:00", "noon", "Midnight",
+    ];
+
+    #[test]
+    fn accepts_every_form() {
+        for input in DURATIONS {
+            assert!(parse_duration(input).is_ok(), "{:?}", input);
+        }
+        for input in TIMES {
+            assert!(parse_time_of_day(input).is_ok(), "{:?}", input);
+        }
+    }
+
+    fn duration(input: &str) -> u32 {
+        parse_duration(input).unwrap_or_else(|e| panic!("{}: {}", input, e))
+    }
+
+    fn time(input: &str) -> u32 {
+        parse_time_of_day(input).unwrap_or_else(|e| panic!("{}: {}", input, e))
+    }
+
+    fn hm(hours: u32, minutes: u32) -> u32 {
+        hours * 3600 + minutes * 60
+    }
+
+    #[test]
+    fn durations_with_units() {
+        assert_eq!(duration("30m"), 30 * 60);
+        assert_eq!(duration("1h30m"), hm(1, 30));
+        assert_eq!(duration("1h 30m"), hm(1, 30));
+        assert_eq!(duration("90min"), hm(1, 30));
+        assert_eq!(duration("90 minutes"), hm(1, 30));
+        assert_eq!(duration("1.5h"), hm(1, 30));
+        assert_eq!(duration(".5h"), hm(0, 30));
+        assert_eq!(duration("2 hours 15 minutes"), hm(2, 15));
+        assert_eq!(duration("45s"), 45);
+        assert_eq!(duration("1H30M"), hm(1, 30));
+        assert_eq!(duration("  2h  "), hm(2, 0));
+    }
+
+    #[test]
+    fn clock_durations() {
+        assert_eq!(duration("1:30"), hm(1, 30));
+        assert_eq!(duration("0:45"), hm(0, 45));
+        assert_eq!(duration("1:00:30"), hm(1, 0) + 30);
+        assert_eq!(duration("36:00"), hm(36, 0));
+    }
+
+    #[test]
+    fn invalid_durations() {
+        assert_eq!(
+            parse_duration(""),
+            Err(TimeParseError::Empty { what: "duration" })
+        );
+        assert!(matches!(
+            parse_duration("1h30"),
+            Err(TimeParseError::MissingUnit { .. })
+        ));
+        assert!(matches!(
+            parse_duration("1 fortnight"),
+            Err(TimeParseError::UnknownUnit { .. })
+        ));
+        assert!(matches!(
+            parse_duration("1h2h"),
+            Err(TimeParseError::DuplicateUnit { .. })
+        ));
+        assert!(matches!(
+            parse_duration("1:75"),
+            Err(TimeParseError::OutOfRange {
+                field: "minute",
+                ..
+            })
+        ));
+        assert!(matches!(
+            parse_duration("1:5"),
+            Err(TimeParseError::UnexpectedToken { .. })
+        ));
+        assert!(matches!(
+            parse_duration("abc"),
+            Err(TimeParseError::UnexpectedToken { .. })
+        ));
+    }
+
+    #[test]
+    fn overlong_durations_are_errors() {
+        assert!(matches!(
+            parse_duration("5000000:00"),
+            Err(TimeParseError::TooLong { .. })
+        ));
+        assert!(matches!(
+            parse_duration("99999999999h"),
+            Err(TimeParseError::TooLong { .. })
+        ));
+        assert_eq!(duration("1193046h"), 1193046 * 3600);
+    }
+
+    #[test]
+    fn twelve_hour_times() {
+        assert_eq!(time("7am"), hm(7, 0));
+        assert_eq!(time("7 AM"), hm(7, 0));
+        assert_eq!(time("7:00 am"), hm(7, 0));
+        assert_eq!(time("7:30 pm"), hm(19, 30));
+        assert_eq!(time("930am"), hm(9, 30));
+        assert_eq!(time("1230pm"), hm(12, 30));
+        assert_eq!(time("7 p.m."), hm(19, 0));
+        assert_eq!(time("7p"), hm(19, 0));
+    }
+
+    #[test]
+    fn meridiem_edge_cases() {
+        assert_eq!(time("12am"), 0);
+        assert_eq!(time("12:30 am"), hm(0, 30));
+        assert_eq!(time("12pm"), hm(12, 0));
+        assert_eq!(time("12:30 pm"), hm(12, 30));
+        assert!(matches!(
+            parse_time_of_day("0am"),
+            Err(TimeParseError::OutOfRange { field: "hour", .. })
+        ));
+        assert!(matches!(
+            parse_time_of_day("13pm"),
+            Err(TimeParseError::OutOfRange { field: "hour", .. })
+        ));
+        assert!(matches!(
+            parse_time_of_day("7 xm"),
+            Err(TimeParseError::UnexpectedToken { .. })
+        ));
+    }
+
+    #[test]
+    fn twenty_four_hour_times() {
+        assert_eq!(time("7"), hm(7, 0));
+        assert_eq!(time("13:00"), hm(13, 0));
+        assert_eq!(time("1330"), hm(13, 30));
+        assert_eq!(time("13:00:30"), hm(13, 0) + 30);
+        assert_eq!(time("0:00"), 0);
+        assert_eq!(time("24:00"), hm(24, 0));
+        assert!(matches!(
+            parse_time_of_day("24:01"),
+            Err(TimeParseError::OutOfRange { .. })
+        ));
+        assert!(matches!(
+            parse_time_of_day("25:00"),
+            Err(TimeParseError::OutOfRange { field: "hour", .. })
+        ));
+        assert!(matches!(
+            parse_time_of_day("13:60"),
+            Err(TimeParseError::OutOfRange {
+                field: "minute",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn named_times() {
+        assert_eq!(time("noon"), hm(12, 0));
+        assert_eq!(time("Midnight"), 0);
+        assert_eq!(
+            parse_time_of_day("  "),
+            Err(TimeParseError::Empty { what: "time" })
+        );
+    }
+
+    #[test]
+    fn formatting() {
+        assert_eq!(format_time_of_day(0), "12:00 am");
+        assert_eq!(format_time_of_day(19 * 60 + 5), "7:05 pm");
+        assert_eq!(format_duration(45.0), "45m");
+        assert_eq!(format_duration(150.0), "2.5h");
+        assert_eq!(canonical_duration(hm(1, 30)), "1h30m");
+        assert_eq!(canonical_duration(90), "1m30s");
+        assert_eq!(canonical_duration(0), "0m");
+    }
+}
@@ -0,0 +1,351 @@
+use super::{cli_available, CliRepo, Repo};
+use crate::internal::*;
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, TimeZone};
+use git2::{
+    Cred, CredentialType, FetchOptions, IndexAddOption, PushOptions, RemoteCallbacks, Repository,
+    Sort, StatusOptions,
+};
+use std::path::{Path, PathBuf};
+
+/// Runs git operations in-process with libgit2.
+///
+/// Fetching and pushing fall back to the `git` command when the built-in
+/// transport fails and git is installed, as the `git` command knows about
+/// the user's credential helpers and SSH configuration.
+pub struct BuiltinRepo {
+    root: PathBuf,
+    repo: Repository,
+}
+
+impl BuiltinRepo {
+    /// Opens the repository whose working tree is at `root`.
+    pub fn open(root: &Path) -> Result<Self> {
+        let repo = Repository::open(root).map_err(git_error("open"))?;
+        Ok(Self {
+            root: root.to_path_buf(),
+            repo,
+        })
+    }
+
+    fn fetch_builtin(&self) -> std::result::Result<(), git2::Error> {
+        let remote_name = self.upstream_remote()?;
+        let mut remote = self.repo.find_remote(&remote_name)?;
+        let mut options = FetchOptions::new();
+        options.remote_callbacks(remote_callbacks());
+        remote.fetch(&[] as &[&str], Some(&mut options), None)
+    }
+
+    fn push_builtin(&self) -> std::result::Result<(), git2::Error> {
+        let head = self.repo.head()?;
+        let local = head
+            .name()
+            .ok_or_else(|| git2::Error::from_str("HEAD is not a branch"))?;
+        let remote_name = self.upstream_remote()?;
+        let merge = self
+            .repo
+            .branch_upstream_merge(local)
+            .ok()
+            .and_then(|buf| buf.as_str().map(|s| s.to_string()))
+            .unwrap_or_else(|| local.to_string());
+
+        let mut remote = self.repo.find_remote(&remote_name)?;
+        let mut callbacks = remote_callbacks();
+        callbacks.push_update_reference(|refname, status| match status {
+            Some(message) => Err(git2::Error::from_str(&format!(
+                "{} was rejected: {}",
+                refname, message
+            ))),
+            None => Ok(()),
+        });
+        let mut options = PushOptions::new();
+        options.remote_callbacks(callbacks);
+        remote.push(&[format!("{}:{}", local, merge)], Some(&mut options))
+    }
+
+    /// The remote the current branch tracks, or "origin".
+    fn upstream_remote(&self) -> std::result::Result<String, git2::Error> {
+        let head = self.repo.head()?;
+        let remote = head
+            .name()
+            .and_then(|name| self.repo.branch_upstream_remote(name).ok())
+            .and_then(|buf| buf.as_str().map(|s| s.to_string()));
+        Ok(remote.unwrap_or_else(|| "origin".to_string()))
+    }
+
+    /// Whether `path` differs between the commit and its first parent.
+    fn changed_in(&self, commit: &git2::Commit, path: &Path) -> Result<bool> {
+        let entry_id = |commit: &git2::Commit| -> Result<Option<git2::Oid>> {
+            let tree = commit.tree().map_err(git_error("log"))?;
+            Ok(tree.get_path(path).ok().map(|entry| entry.id()))
+        };
+        let current = entry_id(commit)?;
+        let previous = match commit.parent(0) {
+            Ok(parent) => entry_id(&parent)?,
+            Err(_) => None,
+        };
+        Ok(current != previous)
+    }
+}
+
+impl Repo for BuiltinRepo {
+    fn status(&self) -> Result<Vec<String>> {
+        let mut options = StatusOptions::new();
+        options
+            .include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .exclude_submodules(true);
+        let statuses = self
+            .repo
+            .statuses(Some(&mut options))
+            .map_err(git_error("status"))?;
+        Ok(statuses
+            .iter()
+            .filter(|entry| !entry.status().is_ignored())
+            .filter_map(|entry| entry.path().map(|path| path.to_string()))
+            .collect())
+    }
+
+    fn add(&self, path: &str) -> Result<()> {
+        let mut index = self.repo.index().map_err(git_error("add"))?;
+        let result = if self.root.join(path).exists() {
+            index.add_all([path], IndexAddOption::DEFAULT, None)
+        } else {
+            index.remove_path(Path::new(path))
+        };
+        result.and_then(|_| index.write()).map_err(git_error("add"))
+    }
+
+    fn commit(&self, message: &str) -> Result<()> {
+        let repo = &self.repo;
+        let result = (|| {
+            let signature = repo.signature()?;
+            let mut index = repo.index()?;
+            let tree = repo.find_tree(index.write_tree()?)?;
+            let parent = match repo.head() {
+                Ok(head) => Some(head.peel_to_commit()?),
+                Err(_) => None,
+            };
+            if let Some(parent) = &parent {
+                if parent.tree_id() == tree.id() {
+                    return Err(git2::Error::from_str(NOTHING_TO_COMMIT));
+                }
+            }
+            let parents: Vec<&git2::Commit> = parent.iter().collect();
+            repo.commit(
+                Some("HEAD"),
+                &signature,
+                &signature,
+                message,
+                &tree,
+                &parents,
+            )
+        })();
+        result.map(|_| ()).map_err(git_error("commit"))
+    }
+
+    fn fetch(&self) -> Result<()> {
+        match self.fetch_builtin() {
+            Ok(()) => Ok(()),
+            Err(e) => fall_back("fetch", e, || CliRepo::new(&self.root).fetch()),
+        }
+    }
+
+    fn push(&self) -> Result<()> {
+        match self.push_builtin() {
+            Ok(()) => Ok(()),
+            Err(e) => fall_back("push", e, || CliRepo::new(&self.root).push()),
+        }
+    }
+
+    fn file_at(&self, rev: &str, path: &str) -> Result<String> {
+        let command = format!("show {}:{}", rev, path);
+        let blob = (|| {
+            let tree = self.repo.revparse_single(rev)?.peel_to_tree()?;
+            let entry = tree.get_path(Path::new(path))?;
+            entry.to_object(&self.repo)?.peel_to_blob()
+        })()
+        .map_err(git_error(&command))?;
+        Ok(String::from_utf8_lossy(blob.content()).to_string())
+    }
+
+    fn last_change_as_of(&self, path: &str, date: NaiveDate) -> Result<Option<(String, String)>> {
+        // Same as `git log --before="<date> 23:59:59"`, in local time
+        let end_of_day = date.and_hms_opt(23, 59, 59).unwrap();
+        let before = match Local.from_local_datetime(&end_of_day).latest() {
+            Some(time) => time.timestamp(),
+            None => end_of_day.and_utc().timestamp(),
+        };
+
+        if self.repo.head().is_err() {
+            return Ok(None);
+        }
+        let mut revwalk = self.repo.revwalk().map_err(git_error("log"))?;
+        revwalk
+            .push_head()
+            .and_then(|_| revwalk.set_sorting(Sort::TIME))
+            .map_err(git_error("log"))?;
+
+        for oid in revwalk {
+            let commit = oid
+                .and_then(|oid| self.repo.find_commit(oid))
+                .map_err(git_error("log"))?;
+            let time = commit.committer().when();
+            if time.seconds() > before || !self.changed_in(&commit, Path::new(path))? {
+                continue;
+            }
+            let hash = commit
+                .as_object()
+                .short_id()
+                .map_err(git_error("log"))?
+                .as_str()
+                .unwrap_or_default()
+                .to_string();
+            let date = FixedOffset::east_opt(time.offset_minutes() * 60)
+                .and_then(|offset| DateTime::from_timestamp(time.seconds(), 0).map(|t| (t, offset)))
+                .map(|(t, offset)| t.with_timezone(&offset).format("%Y-%m-%d").to_string())
+                .unwrap_or_default();
+            return Ok(Some((hash, date)));
+        }
+        Ok(None)
+    }
+}
+
+pub fn clone(url: &str, dest: &Path, shallow: bool) -> Result<()> {
+    let existed = dest.exists();
+    let mut options = FetchOptions::new();
+    options.remote_callbacks(remote_callbacks());
+    if shallow {
+        options.depth(1);
+    }
+    let result = git2::build::RepoBuilder::new()
+        .fetch_options(options)
+        .clone(url, dest);
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => {
+            if !existed {
+                let _ = std::fs::remove_dir_all(dest);
+            }
+            // The URL may include an access token, so it's left out of errors
+            fall_back(&format!("clone {}", dest.display()), e, || {
+                super::cli::clone(url, dest, shallow)
+            })
+        }
+    }
+}
+
+/// Retries a fetch, push or clone with the `git` command when it's
+/// installed.  If that fails too, the built-in error is kept in the report
+/// as it's often the more telling one.
+fn fall_back(command: &str, error: git2::Error, retry: impl FnOnce() -> Result<()>) -> Result<()> {
+    if !cli_available() {
+        return Err(git_error(command)(error));
+    }
+    retry().map_err(|e| match e {
+        Error::Git { command, stderr } => Error::Git {
+            command,
+            stderr: format!("{}\nbuilt-in git: {}", stderr, error.message()),
+        },
+        e => e,
+    })
+}
+
+/// Callbacks that authenticate with the SSH agent, the user's credential
+/// helper, or a token given in the URL.
+fn remote_callbacks() -> RemoteCallbacks<'static> {
+    let mut callbacks = RemoteCallbacks::new();
+    let mut attempts = 0;
+    callbacks.credentials(move |url, username, allowed| {
+        // libgit2 keeps asking while the credentials are rejected
+        attempts += 1;
+        if attempts > 3 {
+            return Err(git2::Error::from_str("authentication failed"));
+        }
+        if allowed.contains(CredentialType::SSH_KEY) {
+            return Cred::ssh_key_from_agent(username.unwrap_or("git"));
+        }
+        if allowed.contains(CredentialType::USER_PASS_PLAINTEXT) {
+            if let Ok(config) = git2::Config::open_default() {
+                if let Ok(cred) = Cred::credential_helper(&config, url, username) {
+                    return Ok(cred);
+                }
+            }
+            return Err(git2::Error::from_str(&format!(
+                "no credentials for {}: set up a git credential helper",
+                url
+            )));
+        }
+        Cred::default()
+    });
+    callbacks
+}
+
+/// What `git commit` says when nothing is staged.
+const NOTHING_TO_COMMIT: &str = "nothing to commit, working tree clean";
+
+fn git_error(command: &str) -> impl Fn(git2::Error) -> Error + '_ {
+    move |e| Error::Git {
+        command: command.to_string(),
+        stderr: e.message().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commit_refuses_when_nothing_changed() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        let repository = Repository::init(root).unwrap();
+        let mut config = repository.config().unwrap();
+        config.set_str("user.name", "Test").unwrap();
+        config.set_str("user.email", "test@example.com").unwrap();
+
+        let repo = BuiltinRepo::open(root).unwrap();
+        std::fs::write(root.join("plan.yaml"), "daily_routine: []\n").unwrap();
+        repo.add("plan.yaml").unwrap();
+        repo.commit("First").unwrap();
+
+        repo.add("plan.yaml").unwrap();
+        let error = repo.commit("Second").unwrap_err();
+        assert!(matches!(
+            error,
+            Error::Git { ref stderr, .. } if stderr == NOTHING_TO_COMMIT
+        ));
+
+        std::fs::write(root.join("plan.yaml"), "daily_routine: []\n# edited\n").unwrap();
+        repo.add("plan.yaml").unwrap();
+        repo.commit("Second").unwrap();
+        let head = repository.head().unwrap().peel_to_commit().unwrap();
+        assert_eq!(head.message(), Some("Second"));
+        assert_eq!(head.parent_count(), 1);
+    }
+
+    #[test]
+    fn failed_fetch_reports_the_builtin_error() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        let repository = Repository::init(root).unwrap();
+        let mut config = repository.config().unwrap();
+        config.set_str("user.name", "Test").unwrap();
+        config.set_str("user.email", "test@example.com").unwrap();
+        let missing = root.join("missing");
+        repository
+            .remote("origin", &format!("file://{}", missing.display()))
+            .unwrap();
+
+        let repo = BuiltinRepo::open(root).unwrap();
+        std::fs::write(root.join("plan.yaml"), "daily_routine: []\n").unwrap();
+        repo.add("plan.yaml").unwrap();
+        repo.commit("First").unwrap();
+
+        let builtin = repo.fetch_builtin().unwrap_err();
+        match repo.fetch().unwrap_err() {
+            Error::Git { stderr, .. } => assert!(stderr.contains(builtin.message())),
+            error => panic!("not a git error: {}", error),
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use super::Repo;
+use crate::internal::*;
+use chrono::NaiveDate;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Runs the `git` command in the repository.
+pub struct CliRepo {
+    root: PathBuf,
+}
+
+impl CliRepo {
+    /// A repository whose working tree is at `root`.
+    pub fn new(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+        }
+    }
+
+    /// Runs git in the repository and returns its trimmed output.
+    pub fn run_git_cmd(&self, args: &[&str]) -> Result<String> {
+        run_git(args, Some(&self.root), &args.join(" "))
+    }
+}
+
+impl Repo for CliRepo {
+    fn status(&self) -> Result<Vec<String>> {
+        let output = self.run_git_cmd(&["status", "--porcelain"])?;
+        Ok(output
+            .lines()
+            .filter_map(|line| line.get(3..))
+            .map(|path| path.to_string())
+            .collect())
+    }
+
+    fn add(&self, path: &str) -> Result<()> {
+        self.run_git_cmd(&["add", path])?;
+        Ok(())
+    }
+
+    fn commit(&self, message: &str) -> Result<()> {
+        self.run_git_cmd(&["commit", "-m", message])?;
+        Ok(())
+    }
+
+    fn fetch(&self) -> Result<()> {
+        self.run_git_cmd(&["fetch"])?;
+        Ok(())
+    }
+
+    fn push(&self) -> Result<()> {
+        self.run_git_cmd(&["push"])?;
+        Ok(())
+    }
+
+    fn file_at(&self, rev: &str, path: &str) -> Result<String> {
+        self.run_git_cmd(&["show", &format!("{}:{}", rev, path)])
+    }
+
+    fn last_change_as_of(&self, path: &str, date: NaiveDate) -> Result<Option<(String, String)>> {
+        let before = format!("--before={} 23:59:59", date.format("%Y-%m-%d"));
+        let rev = self.run_git_cmd(&["log", "-1", "--format=%h %cs", &before, "--", path])?;
+        Ok(rev
+            .split_once(' ')
+            .map(|(hash, date)| (hash.to_string(), date.to_string())))
+    }
+}
+
+pub fn clone(url: &str, dest: &Path, shallow: bool) -> Result<()> {
+    let dest = dest.to_string_lossy();
+    let mut args = vec!["clone"];
+    if shallow {
+        args.extend(["--depth", "1"]);
+    }
+    args.extend([url, &dest]);
+    // The URL may include an access token, so it's left out of errors
+    run_git(&args, None, &format!("clone {}", dest))?;
+    Ok(())
+}
+
+fn run_git(args: &[&str], dir: Option<&Path>, command: &str) -> Result<String> {
+    let mut cmd = Command::new("git");
+    cmd.args(args);
+    if let Some(dir) = dir {
+        cmd.current_dir(dir);
+    }
+    let output = cmd.output()?;
+
+    if !output.status.success() {
+        return Err(Error::Git {
+            command: command.to_string(),
+            // Some failures, e.g. "nothing to commit", are only on stdout
+            stderr: match String::from_utf8_lossy(&output.stderr).trim() {
+                "" => String::from_utf8_lossy(&output.stdout).trim().to_string(),
+                stderr => stderr.to_string(),
+            },
+        });
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
@@ -0,0 +1,100 @@
+//! Git operations on the guidebook data repository.
+//!
+//! [`Repo`] is implemented in-process with libgit2 ([`BuiltinRepo`]) and by
+//! running the `git` command ([`CliRepo`]).  The built-in implementation is
+//! used by default so common commands don't spawn processes and git doesn't
+//! need to be installed.  Set `GUIDEBOOK_PLAN_GIT=cli` to always use the
+//! `git` command instead.
+//!
+mod builtin;
+mod cli;
+
+pub use builtin::BuiltinRepo;
+pub use cli::CliRepo;
+
+use crate::internal::*;
+use chrono::NaiveDate;
+use std::path::Path;
+
+/// Environment variable used to choose the git implementation.
+pub const GIT_BACKEND_VAR: &str = "GUIDEBOOK_PLAN_GIT";
+
+/// The git operations guidebook-plan needs on the data repository.
+pub trait Repo {
+    /// Paths, relative to the repository root, of files that are modified,
+    /// staged, or untracked.  Empty when the working tree is clean.
+    fn status(&self) -> Result<Vec<String>>;
+
+    /// Stages a file given its path relative to the repository root.
+    fn add(&self, path: &str) -> Result<()>;
+
+    /// Commits the staged changes.  Fails with [`Error::Git`] when nothing
+    /// has changed since the last commit.
+    fn commit(&self, message: &str) -> Result<()>;
+
+    /// Fetches from the remote of the current branch.
+    fn fetch(&self) -> Result<()>;
+
+    /// Pushes the current branch to its remote.
+    fn push(&self) -> Result<()>;
+
+    /// Reads a file as it was at the given revision.
+    fn file_at(&self, rev: &str, path: &str) -> Result<String>;
+
+    /// Finds the most recent commit that changed `path` made on or before
+    /// the given date.  Returns the abbreviated commit hash and the commit
+    /// date.
+    fn last_change_as_of(&self, path: &str, date: NaiveDate) -> Result<Option<(String, String)>>;
+}
+
+/// Which implementation of [`Repo`] to use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    /// In-process, with libgit2 ([`BuiltinRepo`])
+    Builtin,
+    /// The `git` command ([`CliRepo`])
+    Cli,
+}
+
+impl Backend {
+    /// The backend chosen with `GUIDEBOOK_PLAN_GIT`, defaulting to the
+    /// built-in one.
+    pub fn from_env() -> Backend {
+        match std::env::var(GIT_BACKEND_VAR).as_deref() {
+            Ok("cli") => Backend::Cli,
+            _ => Backend::Builtin,
+        }
+    }
+}
+
+/// Opens the repository at the given root.  Falls back to the `git`
+/// command if the built-in implementation can't open it, e.g. because it
+/// uses a repository format libgit2 doesn't support.
+pub fn open(root: &Path) -> Result<Box<dyn Repo>> {
+    if Backend::from_env() == Backend::Builtin {
+        match BuiltinRepo::open(root) {
+            Ok(repo) => return Ok(Box::new(repo)),
+            Err(e) if !cli_available() => return Err(e),
+            Err(_) => {}
+        }
+    }
+    Ok(Box::new(CliRepo::new(root)))
+}
+
+/// Clones the repository at `url` into `dest`.  A shallow clone only
+/// fetches the latest commit.
+pub fn clone(url: &str, dest: &Path, shallow: bool) -> Result<()> {
+    match Backend::from_env() {
+        Backend::Builtin => builtin::clone(url, dest, shallow),
+        Backend::Cli => cli::clone(url, dest, shallow),
+    }
+}
+
+/// Whether the `git` command can be run.
+pub fn cli_available() -> bool {
+    std::process::Command::new("git")
+        .arg("--version")
+        .output()
+        .map(|output| output.status.success())
+        .unwrap_or(false)
+}
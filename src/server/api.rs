@@ -0,0 +1,213 @@
+use super::ServerState;
+use crate::internal::*;
+use crate::themes::routine_color;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use chrono::{Datelike, Duration, Local, NaiveDate, Timelike};
+use futures_util::Stream;
+use std::convert::Infallible;
+
+const DAY_MINUTES: u32 = 24 * 60;
+
+/// A scheduled block along with its end, its times formatted as HH:MM, and
+/// the theme color `week` uses for it.
+#[derive(Serialize)]
+pub struct Block {
+    #[serde(flatten)]
+    block: ScheduledBlock,
+    end: u32,
+    start_time: String,
+    end_time: String,
+    color: &'static str,
+}
+
+impl Block {
+    fn new(block: &ScheduledBlock) -> Self {
+        Self {
+            block: block.clone(),
+            end: block.end(),
+            start_time: TimeCursor::new(block.start).pretty(),
+            end_time: TimeCursor::new(block.end()).pretty(),
+            color: routine_color(&block.name),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct Day {
+    day: DayOfWeek,
+    date: NaiveDate,
+    blocks: Vec<Block>,
+    conflicts: Vec<Conflict>,
+    planned_minutes: u32,
+    unplanned_minutes: u32,
+}
+
+impl Day {
+    fn new(date: NaiveDate, schedule: &Schedule) -> Self {
+        Self {
+            day: schedule.day.clone(),
+            date,
+            blocks: schedule.blocks.iter().map(Block::new).collect(),
+            conflicts: schedule.conflicts.clone(),
+            planned_minutes: schedule.planned_minutes(),
+            unplanned_minutes: schedule.unplanned_minutes(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct Now {
+    date: NaiveDate,
+    day: DayOfWeek,
+    /// Time of day as HH:MM
+    time: String,
+    minutes: u32,
+    current: Option<Block>,
+    next: Option<Block>,
+    /// Minutes left in the current block
+    remaining_minutes: Option<u32>,
+}
+
+#[derive(Serialize)]
+pub struct Week {
+    today: NaiveDate,
+    days: Vec<Day>,
+}
+
+/// Errors are returned as `{"error": "..."}`.
+pub struct ApiError(anyhow::Error);
+
+impl<E: Into<anyhow::Error>> From<E> for ApiError {
+    fn from(error: E) -> Self {
+        Self(error.into())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = match self.0.chain().find_map(|e| e.downcast_ref::<Error>()) {
+            Some(Error::MissingRoot(_) | Error::MissingPlan(_)) => StatusCode::NOT_FOUND,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        let body = serde_json::json!({ "error": self.0.to_string() });
+        (status, Json(body)).into_response()
+    }
+}
+
+pub async fn now(State(state): State<ServerState>) -> Result<Json<Now>, ApiError> {
+    Ok(Json(load_now(&state.app())?))
+}
+
+pub async fn today(State(state): State<ServerState>) -> Result<Json<Day>, ApiError> {
+    let (date, schedule) = load_today(&state.app())?;
+    Ok(Json(Day::new(date, &schedule)))
+}
+
+pub async fn week(State(state): State<ServerState>) -> Result<Json<Week>, ApiError> {
+    let app = state.app();
+    let plan = app.load_plan()?;
+    let (today, today_schedule) = load_today(&app)?;
+    let monday = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+
+    let mut days = Vec::new();
+    for (offset, day) in DayOfWeek::week().iter().enumerate() {
+        let date = monday + Duration::days(offset as i64);
+        let schedule = match date == today {
+            true => today_schedule.clone(),
+            false => Schedule::resolve(&plan, day.clone())?,
+        };
+        days.push(Day::new(date, &schedule));
+    }
+    Ok(Json(Week { today, days }))
+}
+
+pub async fn plan(State(state): State<ServerState>) -> Result<Json<Plan>, ApiError> {
+    Ok(Json(state.app().load_plan()?))
+}
+
+/// Sends a "block" event with the same data as `/now` when a client
+/// connects and whenever the current block changes.
+pub async fn events(
+    State(state): State<ServerState>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let stream = futures_util::stream::unfold(None, move |last: Option<String>| {
+        let state = state.clone();
+        async move {
+            let mut first = last.is_none();
+            loop {
+                if !first {
+                    sleep_until_next_minute().await;
+                }
+                first = false;
+
+                let (key, event) = match load_now(&state.app()) {
+                    Ok(now) => {
+                        let key = match &now.current {
+                            Some(b) => format!("{} {} {}", now.date, b.block.index, b.block.start),
+                            None => format!("{} none", now.date),
+                        };
+                        let data = serde_json::to_string(&now).unwrap_or_default();
+                        (key, Event::default().event("block").data(data))
+                    }
+                    Err(e) => {
+                        let message = e.to_string();
+                        let data = serde_json::json!({ "error": message }).to_string();
+                        (message, Event::default().event("error").data(data))
+                    }
+                };
+                if last.as_ref() != Some(&key) {
+                    return Some((Ok(event), Some(key)));
+                }
+            }
+        }
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+/// Today's schedule with today's shifts applied.
+fn load_today(app: &App) -> Result<(NaiveDate, Schedule)> {
+    let plan = app.load_plan()?;
+    let date = Local::now().date_naive();
+    let mut schedule = Schedule::resolve(&plan, plan.current_day_of_week())?;
+    for shift in &app.load_shifts(date)?.shifts {
+        schedule.apply_shift(shift);
+    }
+    Ok((date, schedule))
+}
+
+fn load_now(app: &App) -> Result<Now> {
+    let (date, schedule) = load_today(app)?;
+    let now = Local::now();
+    let minutes = now.hour() * 60 + now.minute();
+
+    let current = schedule.index_at(minutes).map(|i| &schedule.blocks[i]);
+    // The day runs from the start of its first block, so earlier times
+    // belong to the end of the day
+    let day_start = schedule.blocks.first().map(|b| b.start).unwrap_or(0);
+    let time = match minutes < day_start {
+        true => minutes + DAY_MINUTES,
+        false => minutes,
+    };
+    let next = schedule.blocks.iter().find(|b| b.start > time);
+
+    Ok(Now {
+        date,
+        day: schedule.day.clone(),
+        time: TimeCursor::new(minutes).pretty(),
+        minutes,
+        current: current.map(Block::new),
+        next: next.map(Block::new),
+        remaining_minutes: current
+            .map(|b| TimeCursor::new(b.start).minutes_remaining(time, b.duration)),
+    })
+}
+
+async fn sleep_until_next_minute() {
+    let now = Local::now();
+    let elapsed = now.second() as u64 * 1000 + (now.timestamp_subsec_millis() as u64).min(999);
+    tokio::time::sleep(std::time::Duration::from_millis(60_000 - elapsed)).await;
+}
@@ -0,0 +1,19 @@
+use crate::internal::*;
+use axum::response::Html;
+
+const PAGE: &str = include_str!("dashboard.html");
+
+/// The dashboard page, with the theme's colors as CSS variables so it looks
+/// like the terminal output.
+pub async fn index() -> Html<String> {
+    let mut names = cprintln_color_names();
+    names.sort();
+    let variables: Vec<String> = names
+        .iter()
+        .filter(|name| !name.is_empty())
+        .filter(|name| name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'))
+        .map(|name| format!("    --{}: {};", name, cprintln_color_hex(name)))
+        .collect();
+    let theme = format!(":root {{\n{}\n}}", variables.join("\n"));
+    Html(PAGE.replace("/* THEME */", &theme))
+}
@@ -0,0 +1,274 @@
+//! Local HTTP server exposing the resolved plan as JSON, for dashboards and
+//! widgets, along with a built-in dashboard page.  The plan is re-read on
+//! every request so edits and shifts show up without restarting the server.
+//!
+//! The plan is personal data, so the server only listens on localhost,
+//! only answers requests addressed to localhost (which stops web pages
+//! reaching it through DNS rebinding), and only lets pages from other
+//! origins read it when the origin is given with `--allow-origin`.
+//!
+mod api;
+mod dashboard;
+
+use crate::internal::*;
+use axum::extract::{Request, State};
+use axum::http::{header, HeaderValue, Method, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tower_http::cors::CorsLayer;
+
+/// How the server is run.
+#[derive(Debug, Clone, Default)]
+pub struct ServerConfig {
+    /// Home directory the guidebook data is kept under; the user's if None
+    pub home: Option<PathBuf>,
+    /// Port the server listens on, which requests must be addressed to
+    pub port: u16,
+    /// Another origin allowed to read the routes, e.g. a widget's page
+    pub allow_origin: Option<HeaderValue>,
+}
+
+impl ServerConfig {
+    fn app(&self) -> App {
+        match &self.home {
+            Some(home) => App::with_home(home),
+            None => App::new(),
+        }
+    }
+}
+
+type ServerState = Arc<ServerConfig>;
+
+/// The dashboard and JSON routes.
+pub fn router(config: ServerConfig) -> Router {
+    let state = Arc::new(config);
+    let mut router = Router::new()
+        .route("/", get(dashboard::index))
+        .route("/now", get(api::now))
+        .route("/today", get(api::today))
+        .route("/week", get(api::week))
+        .route("/plan", get(api::plan))
+        .route("/events", get(api::events));
+
+    if let Some(origin) = state.allow_origin.clone() {
+        let cors = CorsLayer::new()
+            .allow_origin(origin)
+            .allow_methods([Method::GET]);
+        router = router.layer(cors);
+    }
+    router
+        .layer(middleware::from_fn_with_state(state.clone(), check_host))
+        .with_state(state)
+}
+
+/// Serves the routes on localhost until the process is stopped.
+pub async fn serve(config: ServerConfig) -> Result<()> {
+    // Only listen locally: the plan is personal data
+    let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, config.port));
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    cprintln!(
+        "txt",
+        "Dashboard at [http://localhost:{}](key)",
+        config.port
+    );
+    cprintln!("info", "Press Ctrl+C to stop");
+    axum::serve(listener, router(config)).await?;
+    Ok(())
+}
+
+/// Rejects requests that aren't addressed to this server on localhost.
+async fn check_host(State(state): State<ServerState>, request: Request, next: Next) -> Response {
+    let host = request
+        .headers()
+        .get(header::HOST)
+        .and_then(|host| host.to_str().ok());
+    let allowed = ["localhost", "127.0.0.1"].map(|name| format!("{}:{}", name, state.port));
+    if !host.is_some_and(|host| allowed.iter().any(|a| a == host)) {
+        let body = serde_json::json!({ "error": "requests must be addressed to localhost" });
+        return (StatusCode::FORBIDDEN, Json(body)).into_response();
+    }
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Request;
+    use futures_util::StreamExt;
+    use tempfile::TempDir;
+    use tower::ServiceExt;
+
+    const PORT: u16 = 7777;
+
+    /// Covers the whole day, so there's always a current routine.
+    const PLAN: &str = "\
+daily_routine:
+  - name: Morning
+    start: midnight
+    duration: 12h
+    activities:
+      default:
+        - Coffee
+  - name: Afternoon
+    start: noon
+    duration: 12h
+";
+
+    /// A scratch home directory holding [`PLAN`], and a router for it.
+    fn setup(allow_origin: Option<&str>) -> (TempDir, Router) {
+        let home = TempDir::new().unwrap();
+        let filename = home
+            .path()
+            .join(".local/share/guidebook")
+            .join(PLAN_RELATIVE_PATH);
+        std::fs::create_dir_all(filename.parent().unwrap()).unwrap();
+        std::fs::write(&filename, PLAN).unwrap();
+        let router = router(ServerConfig {
+            home: Some(home.path().to_path_buf()),
+            port: PORT,
+            allow_origin: allow_origin.map(|o| HeaderValue::from_str(o).unwrap()),
+        });
+        (home, router)
+    }
+
+    fn local_request(method: Method, uri: &str) -> axum::http::request::Builder {
+        Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(header::HOST, format!("localhost:{}", PORT))
+    }
+
+    /// Gets a route, returning the home directory too so it outlives the
+    /// response body.
+    async fn get(uri: &str) -> (TempDir, Response) {
+        let (home, router) = setup(None);
+        let request = local_request(Method::GET, uri).body(Body::empty()).unwrap();
+        (home, router.oneshot(request).await.unwrap())
+    }
+
+    async fn get_json(uri: &str) -> serde_json::Value {
+        let (_home, response) = get(uri).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn now() {
+        let now = get_json("/now").await;
+        let current = now["current"]["name"].as_str().unwrap();
+        assert!(["Morning", "Afternoon"].contains(&current));
+        assert!(now["remaining_minutes"].as_u64().unwrap() <= 12 * 60);
+    }
+
+    #[tokio::test]
+    async fn today() {
+        let today = get_json("/today").await;
+        let names: Vec<&str> = today["blocks"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|b| b["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, vec!["Morning", "Afternoon"]);
+        assert_eq!(today["unplanned_minutes"], 0);
+    }
+
+    #[tokio::test]
+    async fn week() {
+        let week = get_json("/week").await;
+        assert_eq!(week["days"].as_array().unwrap().len(), 7);
+    }
+
+    #[tokio::test]
+    async fn plan() {
+        let plan = get_json("/plan").await;
+        assert_eq!(plan["daily_routine"][0]["name"], "Morning");
+        assert_eq!(
+            plan["daily_routine"][0]["activities"]["default"][0],
+            "Coffee"
+        );
+    }
+
+    #[tokio::test]
+    async fn events() {
+        let (_home, response) = get("/events").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "text/event-stream"
+        );
+        let mut stream = response.into_body().into_data_stream();
+        let first = stream.next().await.unwrap().unwrap();
+        let first = String::from_utf8_lossy(&first);
+        assert!(first.starts_with("event: block\n"), "{}", first);
+    }
+
+    #[tokio::test]
+    async fn other_origins_are_not_allowed_by_default() {
+        let (_home, router) = setup(None);
+        let request = local_request(Method::GET, "/plan")
+            .header(header::ORIGIN, "https://example.com")
+            .body(Body::empty())
+            .unwrap();
+        let response = router.oneshot(request).await.unwrap();
+        assert!(!response
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+    }
+
+    #[tokio::test]
+    async fn allowed_origin_can_only_get() {
+        let (_home, router) = setup(Some("http://localhost:3000"));
+        let request = local_request(Method::GET, "/now")
+            .header(header::ORIGIN, "http://localhost:3000")
+            .body(Body::empty())
+            .unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        assert_eq!(
+            response.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+            "http://localhost:3000"
+        );
+
+        let preflight = local_request(Method::OPTIONS, "/now")
+            .header(header::ORIGIN, "http://localhost:3000")
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "GET")
+            .body(Body::empty())
+            .unwrap();
+        let response = router.clone().oneshot(preflight).await.unwrap();
+        assert_eq!(
+            response.headers()[header::ACCESS_CONTROL_ALLOW_METHODS],
+            "GET"
+        );
+
+        let post = local_request(Method::POST, "/now")
+            .body(Body::empty())
+            .unwrap();
+        let response = router.oneshot(post).await.unwrap();
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+    }
+
+    #[tokio::test]
+    async fn other_hosts_are_rejected() {
+        for host in [Some("evil.example.com:7777"), Some("localhost:8080"), None] {
+            let (_home, router) = setup(None);
+            let mut request = Request::get("/plan");
+            if let Some(host) = host {
+                request = request.header(header::HOST, host);
+            }
+            let response = router
+                .oneshot(request.body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::FORBIDDEN, "{:?}", host);
+        }
+    }
+}
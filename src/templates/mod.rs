@@ -0,0 +1,126 @@
+//! Built-in plan templates along with helpers to load a template from a
+//! local path or a remote git repository.
+//!
+use crate::internal::*;
+use std::path::Path;
+
+/// A plan to start from when initializing the data directory.
+pub struct Template {
+    /// Name of a built-in template, or the path or URL it was loaded from
+    pub name: String,
+    /// One-line summary, taken from the first comment in the file
+    pub description: String,
+    /// The template's plan.yaml text, written as-is so its comments are kept
+    pub text: String,
+}
+
+/// The templates that ship with guidebook-plan, as (name, yaml) pairs.
+const BUILT_IN: &[(&str, &str)] = &[
+    ("default", include_str!("default.yaml")),
+    ("maker", include_str!("maker.yaml")),
+    ("parent", include_str!("parent.yaml")),
+    ("shift-worker", include_str!("shift-worker.yaml")),
+    ("student", include_str!("student.yaml")),
+];
+
+impl Template {
+    /// The templates that ship with guidebook-plan.
+    pub fn built_in() -> Vec<Template> {
+        BUILT_IN
+            .iter()
+            .map(|(name, text)| Template::from_text(name, text))
+            .collect()
+    }
+
+    /// Loads a template given a built-in name, a path to a YAML file (or a
+    /// directory containing a plan.yaml), or the URL of a git repository.
+    pub fn resolve(spec: &str) -> Result<Template> {
+        if let Some((name, text)) = BUILT_IN.iter().find(|(name, _)| *name == spec) {
+            return Ok(Template::from_text(name, text));
+        }
+
+        let path = Path::new(spec);
+        let template = if path.exists() {
+            Template::from_path(path)?
+        } else if is_git_url(spec) {
+            Template::from_git(spec)?
+        } else {
+            let names: Vec<&str> = BUILT_IN.iter().map(|(name, _)| *name).collect();
+            return Err(invalid!(
+                "Unknown template '{}'. Use a path, a git URL, or one of: {}",
+                spec,
+                names.join(", ")
+            ));
+        };
+
+        // Make sure the template is a valid plan before anyone uses it
+        template.plan()?;
+        Ok(template)
+    }
+
+    /// Parses and validates the template's plan.
+    pub fn plan(&self) -> Result<Plan> {
+        Plan::from_yaml_validated(&self.text)
+    }
+
+    //------------------------------------------------------------------------//
+    // Loading helpers
+    //------------------------------------------------------------------------//
+
+    /// The description is taken from the first comment line of the file.
+    fn from_text(name: &str, text: &str) -> Template {
+        let description = text
+            .lines()
+            .next()
+            .and_then(|line| line.strip_prefix('#'))
+            .map(|line| line.trim().to_string())
+            .unwrap_or_default();
+        Template {
+            name: name.to_string(),
+            description,
+            text: text.to_string(),
+        }
+    }
+
+    fn from_path(path: &Path) -> Result<Template> {
+        let file = if path.is_dir() {
+            find_plan_file(path)
+                .ok_or_else(|| invalid!("No plan.yaml found in {}", path.display()))?
+        } else {
+            path.to_path_buf()
+        };
+        let text = std::fs::read_to_string(&file)?;
+        let name = file
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        Ok(Template::from_text(&name, &text))
+    }
+
+    fn from_git(url: &str) -> Result<Template> {
+        // Removed when it goes out of scope, whether or not the clone worked
+        let dir = tempfile::TempDir::new()?;
+        let dest = dir.path().join("template");
+        crate::repo::clone(url, &dest, true)?;
+
+        let mut template = Template::from_path(&dest)?;
+        template.name = url.to_string();
+        Ok(template)
+    }
+}
+
+/// Looks for a plan file in the places a template repository might keep it.
+fn find_plan_file(dir: &Path) -> Option<std::path::PathBuf> {
+    ["plan.yaml", PLAN_RELATIVE_PATH, "template.yaml"]
+        .iter()
+        .map(|name| dir.join(name))
+        .find(|path| path.is_file())
+}
+
+fn is_git_url(spec: &str) -> bool {
+    spec.starts_with("https://")
+        || spec.starts_with("http://")
+        || spec.starts_with("git@")
+        || spec.starts_with("ssh://")
+        || spec.ends_with(".git")
+}
@@ -0,0 +1,90 @@
+//! Color themes.  Output uses semantic color names (e.g. `current_name`)
+//! that are registered with cprintln from a built-in theme, optionally
+//! extended by the user's theme file.
+//!
+use crate::internal::*;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// The themes that ship with guidebook-plan, as (name, yaml) pairs.
+const BUILT_IN: &[(&str, &str)] = &[
+    ("dark", include_str!("dark.yaml")),
+    ("light", include_str!("light.yaml")),
+];
+
+/// Other names cprintln knows some of the theme's colors by.
+const ALIASES: &[(&str, &[&str])] = &[("txt", &["text", ""]), ("opt", &["option"])];
+
+/// Routine names are coloured by a hash of the name so the same routine
+/// has the same colour in every column.
+const ROUTINE_COLORS: &[&str] = &[
+    "routine_1",
+    "routine_2",
+    "routine_3",
+    "routine_4",
+    "routine_5",
+    "routine_6",
+    "routine_7",
+    "routine_8",
+];
+
+/// The format of both the built-in themes and the user's theme file.
+#[derive(Debug, Default, Deserialize)]
+struct ThemeFile {
+    /// The built-in theme to start from (defaults to "dark")
+    base: Option<String>,
+    #[serde(default)]
+    colors: BTreeMap<String, String>,
+}
+
+/// Location of the user's theme file, e.g. ~/.config/guidebook-plan/theme.yaml
+pub fn theme_filename() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("guidebook-plan/theme.yaml"))
+}
+
+/// Registers the colors of the user's theme, or of the default theme if
+/// there's no theme file.
+pub fn load_theme() -> Result<()> {
+    let user = match theme_filename() {
+        Some(filename) if filename.exists() => {
+            let text = std::fs::read_to_string(&filename)?;
+            serde_yaml::from_str(&text)
+                .map_err(|e| anyhow!("Invalid theme file {}: {}", filename.display(), e))?
+        }
+        _ => ThemeFile::default(),
+    };
+
+    let base = built_in_colors(user.base.as_deref().unwrap_or("dark"))?;
+    for (name, value) in base.iter().chain(&user.colors) {
+        cprintln_add_color(name, value);
+        for (_, aliases) in ALIASES.iter().filter(|(n, _)| n == name) {
+            for alias in aliases.iter() {
+                cprintln_add_color(alias, value);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The colors of a built-in theme, e.g. for files that are printed rather
+/// than shown in the terminal.
+pub fn built_in_colors(name: &str) -> Result<BTreeMap<String, String>> {
+    let Some((_, text)) = BUILT_IN.iter().find(|(n, _)| *n == name) else {
+        let names: Vec<&str> = BUILT_IN.iter().map(|(name, _)| *name).collect();
+        return Err(anyhow!(
+            "Unknown base theme '{}'. Use one of: {}",
+            name,
+            names.join(", ")
+        ));
+    };
+    let theme: ThemeFile = serde_yaml::from_str(text)?;
+    Ok(theme.colors)
+}
+
+/// The theme color of a routine, shared by the week view, exports and the web dashboard.
+pub fn routine_color(name: &str) -> &'static str {
+    let hash = name.to_lowercase().bytes().fold(0usize, |hash, b| {
+        hash.wrapping_mul(31).wrapping_add(b as usize)
+    });
+    ROUTINE_COLORS[hash % ROUTINE_COLORS.len()]
+}
@@ -2,11 +2,15 @@
 //! printing.  It is not Markdown, just Markdown-like in structure.
 //!
 //! The [text](url) link syntax is used not for links but to format sections
-//! of text, for example with a hex color.
+//! of text, for example with a hex color.  Real URLs (http, https, mailto)
+//! are output as terminal hyperlinks.  Bold, italic, `code`, and
+//! ~~strikethrough~~ are output as the equivalent terminal styles.
 //!
 
 use comrak::nodes::{AstNode, NodeValue};
 use std::collections::HashMap;
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicU8, Ordering};
 use std::sync::{Mutex, OnceLock};
 
 //===========================================================================//
@@ -16,7 +20,7 @@ use std::sync::{Mutex, OnceLock};
 #[macro_export]
 macro_rules! cprintln {
     ($color:expr, $($arg:tt)*) => {
-        $crate::util::cprintln::cprintln_imp($color, &format!($($arg)*))
+        $crate::ui::cprintln::cprintln_imp($color, &format!($($arg)*))
     };
 }
 pub use cprintln;
@@ -24,7 +28,7 @@ pub use cprintln;
 #[macro_export]
 macro_rules! cprint {
     ($color:expr, $($arg:tt)*) => {
-        $crate::util::cprintln::cprint_imp($color, &format!($($arg)*))
+        $crate::ui::cprintln::cprint_imp($color, &format!($($arg)*))
     };
 }
 pub use cprint;
@@ -42,12 +46,12 @@ pub fn cprintln_imp(color: &str, msg: &str) {
 pub fn cprint_imp(color: &str, msg: &str) {
     let base_color = parse_color(color);
     let (msg, trailing_ws) = {
-        let trimmed = msg.trim_end_matches(|c: char| c == ' ' || c == '\t');
+        let trimmed = msg.trim_end_matches([' ', '\t']);
         let ws = &msg[trimmed.len()..];
         (trimmed, ws)
     };
     let (msg, leading_ws) = if !msg.contains('\n') {
-        let trimmed = msg.trim_start_matches(|c: char| c == ' ' || c == '\t');
+        let trimmed = msg.trim_start_matches([' ', '\t']);
         let ws = &msg[..msg.len() - trimmed.len()];
         (trimmed, ws)
     } else {
@@ -63,11 +67,102 @@ pub fn cprint_imp(color: &str, msg: &str) {
             ansi_rgb(base_color.0, base_color.1, base_color.2),
             processed_msg,
             trailing_ws,
-            ANSI_RESET,
+            ansi_reset(),
         );
     }
 }
 
+/// The text of a cprintln message without any of its formatting, e.g. for
+/// measuring or truncating it.
+pub fn cprintln_plain_text(msg: &str) -> String {
+    use comrak::{parse_document, Arena, ComrakOptions};
+
+    let arena = Arena::new();
+    let mut options = ComrakOptions::default();
+    options.extension.strikethrough = true;
+    let root = parse_document(&arena, msg, &options);
+    extract_text_content(root)
+}
+
+//===========================================================================//
+// Terminal color support
+//===========================================================================//
+
+/// Whether to color the output, as chosen with the `--color` flag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ColorChoice {
+    /// Color when writing to a terminal that supports it
+    Auto,
+    /// Color even when piped, e.g. to a pager, using at least 16 colors
+    Always,
+    /// Never color, the same as setting NO_COLOR
+    Never,
+}
+
+/// The colors the terminal is able to display.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ColorLevel {
+    None = 1,
+    Ansi16 = 2,
+    Ansi256 = 3,
+    TrueColor = 4,
+}
+
+/// The detected (or chosen) color level; 0 until first used.
+static COLOR_LEVEL: AtomicU8 = AtomicU8::new(0);
+
+/// Sets how colors are output, overriding the detected level for `Always`
+/// and `Never`.
+pub fn cprintln_set_color_choice(choice: ColorChoice) {
+    let level = match choice {
+        ColorChoice::Auto => detect_color_level(),
+        ColorChoice::Always => terminal_color_level().max(ColorLevel::Ansi16),
+        ColorChoice::Never => ColorLevel::None,
+    };
+    COLOR_LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+pub fn color_level() -> ColorLevel {
+    match COLOR_LEVEL.load(Ordering::Relaxed) {
+        1 => ColorLevel::None,
+        2 => ColorLevel::Ansi16,
+        3 => ColorLevel::Ansi256,
+        4 => ColorLevel::TrueColor,
+        _ => {
+            let level = detect_color_level();
+            COLOR_LEVEL.store(level as u8, Ordering::Relaxed);
+            level
+        }
+    }
+}
+
+/// Follows the NO_COLOR convention (https://no-color.org) and leaves out
+/// colors when the output isn't a terminal, e.g. when piped to a file.
+fn detect_color_level() -> ColorLevel {
+    let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+    auto_color_level(no_color, std::io::stdout().is_terminal())
+}
+
+fn auto_color_level(no_color: bool, is_terminal: bool) -> ColorLevel {
+    if no_color || !is_terminal {
+        return ColorLevel::None;
+    }
+    terminal_color_level()
+}
+
+/// The color level advertised by the terminal through COLORTERM and TERM.
+fn terminal_color_level() -> ColorLevel {
+    let colorterm = std::env::var("COLORTERM").unwrap_or_default();
+    if colorterm == "truecolor" || colorterm == "24bit" {
+        return ColorLevel::TrueColor;
+    }
+    match std::env::var("TERM") {
+        Ok(term) if term == "dumb" => ColorLevel::None,
+        Ok(term) if term.contains("256color") => ColorLevel::Ansi256,
+        _ => ColorLevel::Ansi16,
+    }
+}
+
 //===========================================================================//
 // Custom color storage
 //===========================================================================//
@@ -88,6 +183,8 @@ pub fn ensure_custom_colors() -> &'static Mutex<HashMap<String, String>> {
             ("command", "#dbd488"),
             ("success", "#32CD32"),
             ("success_dim", "#80ad80"),
+            ("code", "#d7a"),
+            ("link", "#6af"),
         ];
         let mut colors = HashMap::new();
         for (key, val) in table {
@@ -113,6 +210,20 @@ pub fn cprintln_add_color(name: &str, value: &str) {
     }
 }
 
+/// Names of all the colors in the global color table
+pub fn cprintln_color_names() -> Vec<String> {
+    match ensure_custom_colors().lock() {
+        Ok(colors) => colors.keys().cloned().collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Resolves a color name or value to a "#rrggbb" string, e.g. for use in HTML
+pub fn cprintln_color_hex(color: &str) -> String {
+    let (r, g, b) = parse_color(color);
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
 //===========================================================================//
 // Internal helpers
 //===========================================================================//
@@ -135,7 +246,8 @@ fn process_markdown(text: &str, base_color: (u8, u8, u8)) -> String {
     text = text.replace('\n', "<br/>");
 
     let arena = Arena::new();
-    let options = ComrakOptions::default();
+    let mut options = ComrakOptions::default();
+    options.extension.strikethrough = true;
     let root = parse_document(&arena, text.as_str(), &options);
 
     let mut result = String::new();
@@ -159,8 +271,6 @@ fn collect_text<'a>(node: &'a AstNode<'a>, result: &mut String, base_color: (u8,
         | NodeValue::Table(_)
         | NodeValue::TableRow(_)
         | NodeValue::TableCell
-        | NodeValue::Strong
-        | NodeValue::Strikethrough
         | NodeValue::Superscript
         | NodeValue::Paragraph => {
             // For all these container nodes, just process their children
@@ -169,32 +279,52 @@ fn collect_text<'a>(node: &'a AstNode<'a>, result: &mut String, base_color: (u8,
             }
         }
         NodeValue::List(_) => {
-            let mut i = 1;
-            for item in node.children() {
-                result.push_str(&format!("{}. ", i));
-                i += 1;
+            for (i, item) in node.children().enumerate() {
+                result.push_str(&format!("{}. ", i + 1));
                 collect_text(item, result, base_color);
             }
         }
+        NodeValue::Strong => collect_styled(node, result, base_color, SGR_BOLD),
+        NodeValue::Emph => collect_styled(node, result, base_color, SGR_ITALIC),
+        NodeValue::Strikethrough => collect_styled(node, result, base_color, SGR_STRIKE),
+        NodeValue::Link(link_data) if is_url(&link_data.url) => {
+            let url = &link_data.url;
+            if color_level() == ColorLevel::None {
+                let link_text = extract_text_content(node);
+                result.push_str(&link_text);
+                if link_text != *url {
+                    result.push_str(&format!(" ({})", url));
+                }
+                return;
+            }
+
+            // OSC 8 hyperlink, underlined in the link color
+            let link_color = parse_color("link");
+            result.push_str(&format!("\x1b]8;;{}\x1b\\", url));
+            result.push_str(&ansi_rgb(link_color.0, link_color.1, link_color.2));
+            result.push_str(&sgr(SGR_UNDERLINE.0));
+            for child in node.children() {
+                collect_text(child, result, link_color);
+            }
+            result.push_str(&sgr(SGR_UNDERLINE.1));
+            result.push_str(&ansi_rgb(base_color.0, base_color.1, base_color.2));
+            result.push_str("\x1b]8;;\x1b\\");
+        }
         NodeValue::Link(link_data) => {
             // Special handling for links: treat the URL as a color
-            let link_text = extract_text_content(node);
-            let url = &link_data.url;
-            let link_color = parse_color(url);
-            let reset_sequence = ansi_rgb(base_color.0, base_color.1, base_color.2);
-            result.push_str(&format!(
-                "{}{}{}",
-                ansi_rgb(link_color.0, link_color.1, link_color.2),
-                link_text,
-                reset_sequence
-            ));
+            let link_color = parse_color(&link_data.url);
+            result.push_str(&ansi_rgb(link_color.0, link_color.1, link_color.2));
+            for child in node.children() {
+                collect_text(child, result, link_color);
+            }
+            result.push_str(&ansi_rgb(base_color.0, base_color.1, base_color.2));
         }
         NodeValue::Text(text) => {
             let mut text = text.clone();
 
             // Remove escape characters on escaped whitespace
             if let Some(stripped) = text.strip_prefix('\\') {
-                if stripped.chars().next().map_or(false, |c| c.is_whitespace()) {
+                if stripped.chars().next().is_some_and(|c| c.is_whitespace()) {
                     text = stripped.to_string();
                 }
             }
@@ -205,11 +335,17 @@ fn collect_text<'a>(node: &'a AstNode<'a>, result: &mut String, base_color: (u8,
             result.push_str(text.as_str());
         }
         NodeValue::Code(code) => {
-            result.push_str(&code.literal);
+            let code_color = parse_color("code");
+            result.push_str(&format!(
+                "{}{}{}",
+                ansi_rgb(code_color.0, code_color.1, code_color.2),
+                code.literal,
+                ansi_rgb(base_color.0, base_color.1, base_color.2)
+            ));
         }
         NodeValue::CodeBlock(code_block) => {
             let content = code_block.literal.trim_end();
-            result.push_str(&content);
+            result.push_str(content);
         }
         NodeValue::HtmlInline(html) => {
             match html.as_str() {
@@ -240,6 +376,24 @@ fn collect_text<'a>(node: &'a AstNode<'a>, result: &mut String, base_color: (u8,
     }
 }
 
+/// Processes the children of a node wrapped in an SGR style (on, off) pair.
+fn collect_styled<'a>(
+    node: &'a AstNode<'a>,
+    result: &mut String,
+    base_color: (u8, u8, u8),
+    style: (u8, u8),
+) {
+    result.push_str(&sgr(style.0));
+    for child in node.children() {
+        collect_text(child, result, base_color);
+    }
+    result.push_str(&sgr(style.1));
+}
+
+fn is_url(url: &str) -> bool {
+    url.starts_with("https://") || url.starts_with("http://") || url.starts_with("mailto:")
+}
+
 fn extract_text_content<'a>(node: &'a AstNode<'a>) -> String {
     let mut text = String::new();
     extract_text_recursive(node, &mut text);
@@ -447,10 +601,139 @@ fn parse_color(color: &str) -> (u8, u8, u8) {
     }
 }
 
-/// Helper function to generate ANSI RGB color escape sequences
+/// Helper function to generate ANSI color escape sequences, approximating
+/// the color when the terminal doesn't support 24-bit color
 fn ansi_rgb(r: u8, g: u8, b: u8) -> String {
-    format!("\x1b[38;2;{};{};{}m", r, g, b)
+    match color_level() {
+        ColorLevel::TrueColor => format!("\x1b[38;2;{};{};{}m", r, g, b),
+        ColorLevel::Ansi256 => format!("\x1b[38;5;{}m", ansi256_index(r, g, b)),
+        ColorLevel::Ansi16 => match ansi16_index(r, g, b) {
+            i if i < 8 => format!("\x1b[{}m", 30 + i),
+            i => format!("\x1b[{}m", 90 + i - 8),
+        },
+        ColorLevel::None => String::new(),
+    }
+}
+
+/// SGR codes to turn a style on and off again
+const SGR_BOLD: (u8, u8) = (1, 22);
+const SGR_ITALIC: (u8, u8) = (3, 23);
+const SGR_UNDERLINE: (u8, u8) = (4, 24);
+const SGR_STRIKE: (u8, u8) = (9, 29);
+
+fn sgr(code: u8) -> String {
+    match color_level() {
+        ColorLevel::None => String::new(),
+        _ => format!("\x1b[{}m", code),
+    }
 }
 
 /// ANSI reset sequence to default foreground color
-const ANSI_RESET: &str = "\x1b[39m";
+fn ansi_reset() -> &'static str {
+    match color_level() {
+        ColorLevel::None => "",
+        _ => "\x1b[39m",
+    }
+}
+
+/// Channel values of the 6x6x6 color cube in the 256 color palette
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// The xterm default values of the 16 standard colors
+const ANSI16_PALETTE: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+fn color_distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let d = |x: u8, y: u8| (x as i32 - y as i32).pow(2) as u32;
+    d(a.0, b.0) + d(a.1, b.1) + d(a.2, b.2)
+}
+
+/// The nearest color in the 256 color palette: either from the color cube
+/// or the grayscale ramp.
+fn ansi256_index(r: u8, g: u8, b: u8) -> u8 {
+    let nearest_level = |v: u8| {
+        (0..6)
+            .min_by_key(|&i| (CUBE_LEVELS[i] as i32 - v as i32).abs())
+            .unwrap()
+    };
+    let (ri, gi, bi) = (nearest_level(r), nearest_level(g), nearest_level(b));
+    let cube = (CUBE_LEVELS[ri], CUBE_LEVELS[gi], CUBE_LEVELS[bi]);
+
+    let average = (r as u32 + g as u32 + b as u32) / 3;
+    let gray_index = (average.saturating_sub(3) / 10).min(23) as u8;
+    let gray_value = 8 + gray_index * 10;
+    let gray = (gray_value, gray_value, gray_value);
+
+    if color_distance((r, g, b), gray) < color_distance((r, g, b), cube) {
+        232 + gray_index
+    } else {
+        (16 + 36 * ri + 6 * gi + bi) as u8
+    }
+}
+
+fn ansi16_index(r: u8, g: u8, b: u8) -> u8 {
+    (0..16)
+        .min_by_key(|&i| color_distance((r, g, b), ANSI16_PALETTE[i]))
+        .unwrap() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn greys_use_the_grayscale_ramp() {
+        assert_eq!(ansi256_index(8, 8, 8), 232);
+        assert_eq!(ansi256_index(128, 128, 128), 244);
+        assert_eq!(ansi256_index(238, 238, 238), 255);
+        // Black and white are in the color cube
+        assert_eq!(ansi256_index(0, 0, 0), 16);
+        assert_eq!(ansi256_index(255, 255, 255), 231);
+    }
+
+    #[test]
+    fn primaries_use_the_color_cube() {
+        assert_eq!(ansi256_index(255, 0, 0), 196);
+        assert_eq!(ansi256_index(0, 255, 0), 46);
+        assert_eq!(ansi256_index(0, 0, 255), 21);
+        assert_eq!(ansi256_index(95, 135, 175), 67);
+    }
+
+    #[test]
+    fn nearest_of_the_16_colors() {
+        assert_eq!(ansi16_index(0, 0, 0), 0);
+        assert_eq!(ansi16_index(205, 0, 0), 1);
+        assert_eq!(ansi16_index(255, 0, 0), 9);
+        assert_eq!(ansi16_index(0, 0, 255), 4);
+        assert_eq!(ansi16_index(128, 128, 128), 8);
+        assert_eq!(ansi16_index(255, 255, 255), 15);
+    }
+
+    #[test]
+    fn no_color_turns_colors_off() {
+        assert_eq!(auto_color_level(true, true), ColorLevel::None);
+        assert_eq!(auto_color_level(false, false), ColorLevel::None);
+
+        cprintln_set_color_choice(ColorChoice::Never);
+        assert_eq!(color_level(), ColorLevel::None);
+        assert_eq!(ansi_rgb(255, 0, 0), "");
+        assert_eq!(sgr(SGR_BOLD.0), "");
+        assert_eq!(ansi_reset(), "");
+    }
+}
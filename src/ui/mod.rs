@@ -0,0 +1,9 @@
+//! Terminal output helpers for the command-line front-end.
+//!
+pub mod cprintln;
+mod report;
+mod terminal;
+
+pub use cprintln::*;
+pub use report::*;
+pub use terminal::*;
@@ -0,0 +1,82 @@
+use crate::internal::*;
+
+/// Exit codes for each kind of failure, so scripts can tell them apart.
+/// 2 is left for usage errors, which clap reports itself.
+pub const EXIT_FAILURE: i32 = 1;
+pub const EXIT_MISSING_ROOT: i32 = 3;
+pub const EXIT_MISSING_PLAN: i32 = 4;
+pub const EXIT_PARSE: i32 = 5;
+pub const EXIT_GIT: i32 = 6;
+pub const EXIT_NETWORK: i32 = 7;
+pub const EXIT_AUTH: i32 = 8;
+
+/// Prints a friendly message for the error and returns the exit code to
+/// use for it.
+pub fn report_error(error: &anyhow::Error) -> i32 {
+    let kind = error.chain().find_map(|e| e.downcast_ref::<Error>());
+    match kind {
+        Some(Error::MissingRoot(path)) => {
+            cprintln!(
+                "",
+                r#"
+[ERROR: guidebook data directory not found](error) at [{}](filename)
+
+Run the following command to initialize your guidebook repository:
+[guidebook-plan init](command)
+
+This will create a remote and local data directory as needed.
+"#,
+                path.display()
+            );
+            EXIT_MISSING_ROOT
+        }
+        Some(Error::MissingPlan(path)) => {
+            cprintln!(
+                "",
+                r#"
+[ERROR: plan file not found](error) at [{}](filename)
+
+Run [guidebook-plan init](command) to create one from a template.
+"#,
+                path.display()
+            );
+            EXIT_MISSING_PLAN
+        }
+        Some(Error::Parse { file, .. }) => {
+            let is_plan = file.as_ref().is_none_or(|file| file.ends_with("plan.yaml"));
+            match is_plan {
+                true => cprintln!("error", "ERROR: the plan file could not be read"),
+                false => cprintln!("error", "ERROR: the file could not be read"),
+            }
+            cprintln!("txt", "    {}", error);
+            if is_plan {
+                cprintln!("txt", "Run [guidebook-plan open](command) to fix it.");
+            }
+            EXIT_PARSE
+        }
+        Some(Error::Git { command, stderr }) => {
+            cprintln!("error", "ERROR: [git {}](command) failed", command);
+            for line in stderr.lines() {
+                cprintln!("txt", "    {}", line);
+            }
+            EXIT_GIT
+        }
+        Some(Error::Network(_)) => report_network(error),
+        Some(Error::Auth(message)) => {
+            cprintln!("error", "ERROR: GitHub authorization failed: {}", message);
+            cprintln!("txt", "Run [guidebook-plan init](command) again to retry.");
+            EXIT_AUTH
+        }
+        None if error.chain().any(|e| e.is::<reqwest::Error>()) => report_network(error),
+        _ => {
+            cprintln!("error", "ERROR: {}", error);
+            EXIT_FAILURE
+        }
+    }
+}
+
+fn report_network(error: &anyhow::Error) -> i32 {
+    cprintln!("error", "ERROR: {}", error);
+    cprintln!("txt", "Check your internet connection and try again.");
+    EXIT_NETWORK
+}
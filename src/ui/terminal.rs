@@ -0,0 +1,66 @@
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// Width of the terminal in columns, or a reasonable default when the
+/// output isn't a terminal (e.g. when piped to a file).
+pub fn terminal_width() -> usize {
+    match terminal_size::terminal_size() {
+        Some((terminal_size::Width(width), _)) => width as usize,
+        None => 80,
+    }
+}
+
+/// Truncates text to fit in the given number of columns, marking the cut
+/// with an ellipsis, and pads it with spaces to exactly that width.  Wide
+/// characters, e.g. CJK and most emoji, take two columns.
+pub fn fit_width(text: &str, width: usize) -> String {
+    let text_width = text.width();
+    if text_width <= width {
+        return format!("{}{}", text, " ".repeat(width - text_width));
+    }
+    if width == 0 {
+        return String::new();
+    }
+
+    let mut fitted = String::new();
+    let mut used = 0;
+    for c in text.chars() {
+        let c_width = c.width().unwrap_or(0);
+        if used + c_width > width - 1 {
+            break;
+        }
+        fitted.push(c);
+        used += c_width;
+    }
+    // A wide character that didn't fit leaves a column to pad
+    format!("{}…{}", fitted, " ".repeat(width - 1 - used))
+}
+
+/// A horizontal rule of the given width for use with cprintln!.  The first
+/// dash is escaped so the rule isn't read as a Markdown thematic break,
+/// which would always be drawn 70 characters wide.
+pub fn rule(width: usize) -> String {
+    match width {
+        0 => String::new(),
+        w => format!("\\{}", "-".repeat(w)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fits_text_to_its_width() {
+        assert_eq!(fit_width("Work", 6), "Work  ");
+        assert_eq!(fit_width("Workout", 6), "Worko…");
+        assert_eq!(fit_width("Workout", 0), "");
+        assert_eq!(fit_width("Workout", 1), "…");
+    }
+
+    #[test]
+    fn wide_characters_take_two_columns() {
+        assert_eq!(fit_width("会議", 6), "会議  ");
+        assert_eq!(fit_width("会議室", 5), "会議…");
+        assert_eq!(fit_width("会議室", 4), "会… ");
+    }
+}
@@ -1,5 +1,5 @@
-pub mod cprintln;
+//! Small helpers shared by the library and the command-line tool.
+//!
 mod time_cursor;
 
-pub use cprintln::*;
 pub use time_cursor::*;
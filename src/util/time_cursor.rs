@@ -3,33 +3,37 @@
 ///
 #[derive(Debug, Clone)]
 pub struct TimeCursor {
+    /// Minutes since midnight; may run past 24h for blocks after midnight
     pub cursor: u32,
 }
 
 impl TimeCursor {
+    /// Creates a cursor at `start` minutes after midnight.
     pub fn new(start: u32) -> Self {
         Self { cursor: start }
     }
 
+    /// Moves the cursor to the given minute of the day.
     pub fn set(&mut self, minutes: u32) {
         self.cursor = minutes;
     }
 
+    /// Moves the cursor forward by a number of minutes.
     pub fn add(&mut self, duration: u32) {
         self.cursor += duration;
     }
 
+    /// Minutes left at `now` of a block of `duration` minutes that starts
+    /// at the cursor.
     pub fn minutes_remaining(&self, now: u32, duration: u32) -> u32 {
-        let elapsed = if now > self.cursor {
-            now - self.cursor
-        } else {
-            0
-        };
+        let elapsed = now.saturating_sub(self.cursor);
         duration.saturating_sub(elapsed)
     }
 
+    /// The cursor as HH:MM on a 24-hour clock.
     pub fn pretty(&self) -> String {
-        let hours = self.cursor / 60;
+        // Blocks running past midnight continue on the next day
+        let hours = (self.cursor / 60) % 24;
         let minutes = self.cursor % 60;
         format!("{:02}:{:02}", hours, minutes)
     }